@@ -2,6 +2,7 @@
 # Usage: ./run.sh <log-level>
 # Valid log-levels: off | error | warn | info | debug | trace
 # This script calls cargo run, which means it runs in debug (not release) mode.
+# Set GAME_MAP to load a different map, e.g. GAME_MAP=maps/test_platformer_map ./run.sh info
 
 set -e
 
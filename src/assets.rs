@@ -0,0 +1,107 @@
+//! Virtual asset filesystem.
+//!
+//! Every asset in the game is addressed by a path relative to an asset root, e.g. `"sprites/test_npc.png"`,
+//! and never by a literal filesystem path. The [`AssetResolver`] owns an ordered list of roots and hands back
+//! the first root that actually has the requested file, so a user `mods/` directory placed ahead of the base
+//! `assets/` directory overrides maps, dialogue, sprites, etc. without touching the install.
+//!
+//! All loaders in the crate go through here instead of calling macroquad's `load_*` functions directly.
+use crate::prelude::*;
+use std::cell::RefCell;
+use std::io;
+use std::path::{ Path, PathBuf };
+
+pub struct AssetResolver {
+    /// Searched front to back, first hit wins
+    roots: Vec<PathBuf>,
+    /// Relative asset path -> index into `roots` it was resolved from, for [`AssetResolver::debug_listing`].
+    /// RefCell so loading stays `&self`, as we pass the resolver around by shared reference everywhere.
+    resolved: RefCell<HashMap<Box<str>, usize>>,
+}
+
+impl AssetResolver {
+    pub fn new<P: Into<PathBuf>>(roots: impl IntoIterator<Item = P>) -> Self {
+        AssetResolver {
+            roots: roots.into_iter().map(Into::into).collect(),
+            resolved: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Resolver over [`ASSET_ROOTS`]
+    pub fn with_default_roots() -> Self {
+        AssetResolver::new(ASSET_ROOTS.iter().copied())
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Pushes a root to the front of the search order, i.e. it takes priority over every root already present.
+    pub fn push_front_root<P: Into<PathBuf>>(&mut self, root: P) {
+        self.roots.insert(0, root.into());
+        // Indices into roots just shifted, and anything resolved earlier may now resolve elsewhere
+        self.resolved.get_mut().clear();
+    }
+
+    /// Finds the full path of an asset given its path relative to an asset root.
+    pub fn resolve(&self, asset: &str) -> GResult<PathBuf> {
+        // Asset paths are always relative to a root, so refuse anything that'd escape it
+        if Path::new(asset).is_absolute() || asset.split(['/', '\\']).any(|part| part == "..") {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Asset path '{asset}' must be relative to an asset root")).into());
+        }
+
+        for (root_index, root) in self.roots.iter().enumerate() {
+            let path = root.join(asset);
+            if path.is_file() {
+                // Logged as it happens rather than only in a listing, as plenty (textures) only resolve once the game's running
+                if self.resolved.borrow_mut().insert(Box::from(asset), root_index) != Some(root_index) {
+                    dlog!(Level::Debug, "Asset '{}' <- {}", asset, root.display());
+                }
+                return Ok(path);
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Asset '{asset}' was not found in any asset root {:?}", self.roots)
+        ).into())
+    }
+
+    /// Same as [`AssetResolver::resolve`] but as a `String`, which is what macroquad's loaders want
+    fn resolve_string(&self, asset: &str) -> GResult<String> {
+        self.resolve(asset)?
+            .into_os_string()
+            .into_string()
+            .map_err(|path| io::Error::new(io::ErrorKind::InvalidData, format!("Resolved asset path {path:?} is not valid UTF-8")).into())
+    }
+
+    pub async fn load_file(&self, asset: &str) -> GResult<Vec<u8>> {
+        Ok(load_file(&self.resolve_string(asset)?).await?)
+    }
+
+    pub async fn load_string(&self, asset: &str) -> GResult<String> {
+        Ok(String::from_utf8(self.load_file(asset).await?)?)
+    }
+
+    pub async fn load_texture(&self, asset: &str) -> GResult<Texture2D> {
+        Ok(load_texture(&self.resolve_string(asset)?).await?)
+    }
+
+    pub async fn load_ttf_font(&self, asset: &str) -> GResult<Font> {
+        Ok(load_ttf_font(&self.resolve_string(asset)?).await?)
+    }
+
+    /// Logs (at [`Level::Debug`]) every asset resolved so far and which root it came from.
+    /// Each one's also logged the first time it resolves, see [`AssetResolver::resolve`]
+    pub fn debug_listing(&self) {
+        let resolved = self.resolved.borrow();
+        let mut listing = resolved.iter().collect::<Vec<_>>();
+        listing.sort_unstable();
+
+        let mut out = format!("ASSETS ({} resolved)", listing.len());
+        for (asset, &root_index) in listing {
+            out.push_str(&format!("\n\t{asset} <- {}", self.roots[root_index].display()));
+        }
+        dlog!(Level::Debug, "{}", out);
+    }
+}
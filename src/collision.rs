@@ -0,0 +1,189 @@
+//! Entity movement against solid things, built on [`RectBounded::sweep`] so nothing tunnels through thin geometry
+//! no matter how big a frame's step is, and on [`ShapeBounded::penetration`] for everything that isn't a plain rect.
+pub mod collision_world;
+
+pub use collision_world::*;
+
+use crate::prelude::*;
+
+/// Something with a [`Collider`], either an entity or a piece of map geometry (by index into [`GeometryMap::inner`])
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ColliderRef {
+    Entity(EntityId),
+    Geometry(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OverlapKind {
+    Enter,
+    Exit,
+}
+
+/// An entity started or stopped overlapping a trigger collider
+#[derive(Clone, Copy, Debug)]
+pub struct TriggerEvent {
+    pub trigger: ColliderRef,
+    pub other: EntityId,
+    pub kind: OverlapKind,
+}
+
+/// A surface only needs this many redirections per frame to settle, e.g. into a corner
+const MAX_SLIDES: usize = 4;
+
+/// What a [`move_and_slide`] ran into
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SlideResult {
+    /// Hit something below us, i.e. standing on it
+    pub grounded: bool,
+    /// Hit anything at all
+    pub collided: bool,
+}
+
+/// Moves a mover by `velocity * dt`, stopping at the first contact and sliding along its surface with the rest of the step.
+/// Velocity into any surface hit is removed.
+///
+/// Rect against rect is swept exactly. Anything involving another [`Shape`] is resolved by pushing out of overlaps
+/// afterwards, with the step split up so no piece of it is long enough to pass through the mover's own half width.
+pub fn move_and_slide(mover: &mut Entity, blockers: &[Shape], dt: f32) -> SlideResult {
+    let mut result = SlideResult::default();
+    let sweepable = if mover.shape().as_rect().is_some() {
+        blockers.iter().filter_map(Shape::as_rect).collect::<Vec<_>>()
+    } else {
+        Vec::new()
+    };
+    let needs_substeps = sweepable.len() != blockers.len();
+
+    let max_step = (mover.bsize().min_element() / 2.0).max(1.0);
+    let steps = if needs_substeps {
+        ((mover.velocity() * dt).length() / max_step).ceil().max(1.0) as usize
+    } else {
+        1
+    };
+
+    for _ in 0..steps {
+        // Velocity may have been cut short by the last substep
+        result |= sweep_and_slide(mover, &sweepable, mover.velocity() * dt / steps as f32);
+        result |= depenetrate(mover, blockers);
+    }
+    result
+}
+
+fn sweep_and_slide(mover: &mut Entity, blockers: &[Rect], mut remaining: Vec2) -> SlideResult {
+    let mut result = SlideResult::default();
+
+    for _ in 0..MAX_SLIDES {
+        if remaining == Vec2::ZERO {
+            break;
+        }
+
+        let earliest = blockers.iter()
+            .filter_map(|blocker| mover.sweep(remaining, blocker))
+            .min_by(|a, b| a.time.total_cmp(&b.time));
+
+        let Some(hit) = earliest else {
+            mover.offset(remaining);
+            break;
+        };
+
+        mover.offset(remaining * hit.time);
+        result.collided = true;
+        result.grounded |= hit.normal.y < 0.0;
+
+        // Cancel whatever pushes into the surface, keep what runs along it
+        remaining *= 1.0 - hit.time;
+        remaining -= hit.normal * remaining.dot(hit.normal);
+        let velocity = mover.velocity();
+        *mover.mut_velocity() = velocity - hit.normal * velocity.dot(hit.normal);
+    }
+
+    result
+}
+
+/// Pushes a mover out of anything it's overlapping by the shortest way out, for overlaps sweeping can't
+/// account for (non-rect shapes, spawning inside something, something else moving into us, etc.).
+/// Velocity into whatever pushed us out is removed.
+pub fn depenetrate(mover: &mut Entity, blockers: &[Shape]) -> SlideResult {
+    let mut result = SlideResult::default();
+    for blocker in blockers {
+        let Some(push) = mover.penetration(blocker) else {
+            continue;
+        };
+        // Touching within float error isn't worth a push, same as sweeping
+        if push.length() <= SWEEP_EPSILON {
+            continue;
+        }
+        mover.offset(push);
+        result.collided = true;
+        result.grounded |= push.y < 0.0;
+
+        let normal = push.normalize();
+        let velocity = mover.velocity();
+        let into = velocity.dot(normal);
+        if into < 0.0 {
+            *mover.mut_velocity() = velocity - normal * into;
+        }
+    }
+    result
+}
+
+impl std::ops::BitOrAssign for SlideResult {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.grounded |= rhs.grounded;
+        self.collided |= rhs.collided;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALL: Rect = Rect { x: 100.0, y: -50.0, w: 2.0, h: 100.0 };
+
+    #[test]
+    fn sweep_hits_thin_wall_from_far_away() {
+        let mover = Entity::bare(Rect::new(0.0, 0.0, 10.0, 10.0));
+        let hit = mover.sweep(vec2(1000.0, 0.0), &WALL).unwrap();
+        assert!((hit.time - 0.09).abs() < 1e-6, "{}", hit.time);
+        assert_eq!(hit.normal, vec2(-1.0, 0.0));
+
+        assert!(mover.sweep(vec2(-1000.0, 0.0), &WALL).is_none());
+        assert!(mover.sweep(vec2(1000.0, 1000.0), &WALL).is_none());
+    }
+
+    #[test]
+    fn fast_mover_stops_at_thin_wall() {
+        let mut mover = Entity::bare(Rect::new(0.0, 0.0, 10.0, 10.0));
+        mover.velocity = vec2(60_000.0, 30.0);
+        let result = move_and_slide(&mut mover, &[Shape::Rect(WALL)], 1.0 / 60.0);
+        assert!(result.collided && !result.grounded);
+        assert!((mover.boundary.right() - WALL.left()).abs() <= SWEEP_EPSILON, "{:?}", mover.boundary);
+        assert_eq!(mover.velocity.x, 0.0);
+        // Slid along the wall with the rest
+        assert!(mover.boundary.y > 0.0);
+    }
+
+    #[test]
+    fn fast_mover_stops_at_thin_wall_as_circle() {
+        let mut mover = Entity::bare(Rect::new(0.0, 0.0, 10.0, 10.0))
+            .with_collider(Collider::default().with_shape(ColliderShape::Circle));
+        mover.velocity = vec2(60_000.0, 0.0);
+        move_and_slide(&mut mover, &[Shape::Rect(WALL)], 1.0 / 60.0);
+        assert!(mover.boundary.right() <= WALL.left() + SWEEP_EPSILON, "{:?}", mover.boundary);
+    }
+
+    #[test]
+    fn fast_mover_does_not_tunnel_through_shovable_entity() {
+        let mut player = Entity::bare(Rect::new(0.0, 0.0, 10.0, 10.0))
+            .with_controller(InputControl::default())
+            .with_collider(Collider::default());
+        player.velocity = vec2(6000.0, 0.0);
+        let mut em = EntityManager::new(player);
+        let npc = em.spawn(Entity::bare(Rect::new(50.0, 0.0, 16.0, 10.0)).with_collider(Collider::default()));
+        let map = GeometryMap { inner: vec![], settings: MapSettings::default() };
+
+        CollisionWorld::new().step(&mut em, &map, 1.0 / 60.0);
+        let (player, npc) = (em.ref_player().boundary, em.get(npc).unwrap().boundary);
+        assert!(player.right() <= npc.left() + SWEEP_EPSILON, "{player:?} went through {npc:?}");
+        assert!(npc.left() > 50.0, "{npc:?} wasn't shoved");
+    }
+}
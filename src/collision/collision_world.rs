@@ -0,0 +1,364 @@
+use crate::prelude::*;
+use super::{ ColliderRef, OverlapKind, SlideResult, TriggerEvent, move_and_slide, depenetrate };
+use std::collections::VecDeque;
+
+/// Pushes between entities are redone this many times per step, so a shove into a wall (or a pile) settles
+const PUSH_ITERATIONS: usize = 8;
+/// How far apart (in logical pixels) still counts as touching for [`CollisionEvent::Contact`]
+const CONTACT_SKIN: f32 = 0.5;
+/// Cap on [`substeps`], so a runaway velocity can't stall a frame
+const MAX_SUBSTEPS: usize = 32;
+
+/// Something the [`CollisionWorld`] noticed during a step, see [`CollisionWorld::drain_events`]
+#[derive(Clone, Copy, Debug)]
+pub enum CollisionEvent {
+    /// `entity` started touching something solid to it. `normal` points from `other` toward `entity`
+    Contact {
+        entity: EntityId,
+        other: ColliderRef,
+        normal: Vec2,
+    },
+    /// `entity` stopped touching `other`
+    Separated {
+        entity: EntityId,
+        other: ColliderRef,
+    },
+    Trigger(TriggerEvent),
+}
+
+/// Resolves every entity with a [`Collider`] against every other one and the map.
+///
+/// Movers are swept against whatever can't be shoved (map geometry and immovable entities), then anything
+/// still overlapping gets pushed apart by mass. Since shovable entities aren't swept against, a step is split up
+/// so nothing moves further than the smallest entity's half size at once, and can't end up past it instead of in it.
+/// What happened ends up as [`CollisionEvent`]s for game code to react to.
+#[derive(Debug, Default)]
+pub struct CollisionWorld {
+    /// Who's touching what as of the last step, with the contact normal
+    contacts: HashMap<(EntityId, ColliderRef), Vec2>,
+    /// (trigger, entity) pairs overlapping as of the last step
+    trigger_overlaps: HashSet<(ColliderRef, EntityId)>,
+    events: VecDeque<CollisionEvent>,
+}
+
+impl CollisionWorld {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves every mover by its velocity (already decided for this frame) and resolves the fallout
+    pub fn step(&mut self, em: &mut EntityManager, map: &GeometryMap, dt: f32) {
+        let substeps = substeps(em, dt);
+        // Grounded if it landed in any substep, later ones might not move it down at all
+        for mover in em.iter_mut().filter(|e| e.is_mover()) {
+            mover.platformer.grounded = false;
+        }
+        for _ in 0..substeps {
+            move_movers(em, map, dt / substeps as f32);
+            for _ in 0..PUSH_ITERATIONS {
+                push_apart(em);
+                settle_against_map(em, map);
+            }
+        }
+        self.update_contacts(em, map);
+        self.update_trigger_overlaps(em, map);
+    }
+
+    /// Takes every event since the last drain, oldest first
+    pub fn drain_events(&mut self) -> impl Iterator<Item = CollisionEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    /// (entity, other, normal) for everything touching as of the last step
+    pub fn contacts(&self) -> impl Iterator<Item = (EntityId, ColliderRef, Vec2)> + '_ {
+        self.contacts.iter().map(|(&(entity, other), &normal)| (entity, other, normal))
+    }
+
+    pub fn trigger_overlaps(&self) -> &HashSet<(ColliderRef, EntityId)> {
+        &self.trigger_overlaps
+    }
+
+    fn update_contacts(&mut self, em: &EntityManager, map: &GeometryMap) {
+        let colliders = colliders(em, map).collect::<Vec<_>>();
+
+        let mut contacts = HashMap::new();
+        for entity in em.iter() {
+            let Some(collider) = entity.collider else {
+                continue;
+            };
+            let skin = entity.shape().inflated(CONTACT_SKIN);
+            for (other, shape, other_collider) in colliders.iter() {
+                if *other == ColliderRef::Entity(entity.id) || !collider.is_blocked_by(other_collider) {
+                    continue;
+                }
+                if let Some(push) = skin.penetration(shape) {
+                    contacts.insert((entity.id, *other), push.normalize_or_zero());
+                }
+            }
+        }
+
+        for (&(entity, other), &normal) in contacts.iter() {
+            if !self.contacts.contains_key(&(entity, other)) {
+                self.events.push_back(CollisionEvent::Contact { entity, other, normal });
+            }
+        }
+        for &(entity, other) in self.contacts.keys() {
+            if !contacts.contains_key(&(entity, other)) {
+                self.events.push_back(CollisionEvent::Separated { entity, other });
+            }
+        }
+        self.contacts = contacts;
+    }
+
+    fn update_trigger_overlaps(&mut self, em: &EntityManager, map: &GeometryMap) {
+        let triggers = colliders(em, map).filter(|(_, _, collider)| collider.trigger).collect::<Vec<_>>();
+
+        let mut overlaps = HashSet::new();
+        for entity in em.iter() {
+            let Some(collider) = entity.collider.as_ref() else {
+                continue;
+            };
+            for (trigger, shape, trigger_collider) in triggers.iter() {
+                if *trigger != ColliderRef::Entity(entity.id) && trigger_collider.triggers_on(collider) && entity.intersects_shape(shape) {
+                    overlaps.insert((*trigger, entity.id));
+                }
+            }
+        }
+
+        for &(trigger, other) in overlaps.difference(&self.trigger_overlaps) {
+            self.events.push_back(CollisionEvent::Trigger(TriggerEvent { trigger, other, kind: OverlapKind::Enter }));
+        }
+        for &(trigger, other) in self.trigger_overlaps.difference(&overlaps) {
+            self.events.push_back(CollisionEvent::Trigger(TriggerEvent { trigger, other, kind: OverlapKind::Exit }));
+        }
+        self.trigger_overlaps = overlaps;
+    }
+}
+
+/// Every collider in the world, entities' then the map's
+fn colliders<'a>(em: &'a EntityManager, map: &'a GeometryMap) -> impl Iterator<Item = (ColliderRef, Shape, Collider)> + 'a {
+    em.iter()
+        .filter_map(|e| Some((ColliderRef::Entity(e.id), e.shape(), e.collider?)))
+        .chain(map.inner.iter().enumerate().map(|(i, g)| (ColliderRef::Geometry(i), g.shape(), g.collider)))
+}
+
+/// How many pieces a step needs so no mover goes further than half the smallest solid entity in one, see [`CollisionWorld`]
+fn substeps(em: &EntityManager, dt: f32) -> usize {
+    let max_step = em.iter()
+        .filter(|e| e.collider.is_some_and(|c| !c.trigger))
+        .map(|e| e.bsize().min_element() / 2.0)
+        .fold(f32::INFINITY, f32::min)
+        .max(1.0);
+    let furthest = em.iter()
+        .filter(|e| e.is_mover())
+        .map(|e| e.velocity().length() * dt)
+        .fold(0.0, f32::max);
+    ((furthest / max_step).ceil() as usize).clamp(1, MAX_SUBSTEPS)
+}
+
+fn move_movers(em: &mut EntityManager, map: &GeometryMap, dt: f32) {
+    let movers = em.iter().filter(|e| e.is_mover()).map(Entity::id).collect::<Vec<_>>();
+    for id in movers {
+        // Detached so it can be moved while looking at everyone else
+        let Some(mut mover) = em.detach(id) else {
+            continue;
+        };
+
+        let slide = if let Some(collider) = mover.collider {
+            // Only what can't be shoved out of the way stops us outright, the rest is pushed apart after
+            let blockers = colliders(em, map)
+                .filter(|(other, _, other_collider)| {
+                    (matches!(other, ColliderRef::Geometry(_)) || other_collider.immovable) && collider.is_blocked_by(other_collider)
+                })
+                .map(|(_, shape, _)| shape)
+                .collect::<Vec<_>>();
+            move_and_slide(&mut mover, &blockers, dt)
+        } else {
+            mover.update_x(dt);
+            mover.update_y(dt);
+            SlideResult::default()
+        };
+        mover.platformer.grounded |= slide.grounded;
+
+        em.reattach(mover);
+    }
+}
+
+/// How much of an overlap each of a pair takes, None if neither budges
+fn push_shares(a: &Collider, b: &Collider) -> Option<(f32, f32)> {
+    let a_yields = !a.immovable && a.is_blocked_by(b);
+    let b_yields = !b.immovable && b.is_blocked_by(a);
+    match (a_yields, b_yields) {
+        (true, true) => {
+            let total = a.mass + b.mass;
+            if total <= 0.0 {
+                Some((0.5, 0.5))
+            } else {
+                Some((b.mass / total, a.mass / total))
+            }
+        }
+        (true, false) => Some((1.0, 0.0)),
+        (false, true) => Some((0.0, 1.0)),
+        (false, false) => None,
+    }
+}
+
+/// Pushes overlapping entity pairs apart
+fn push_apart(em: &mut EntityManager) {
+    let ids = em.iter().filter(|e| e.collider.is_some()).map(Entity::id).collect::<Vec<_>>();
+    for (i, &a_id) in ids.iter().enumerate() {
+        for &b_id in &ids[i + 1..] {
+            let (Some(a), Some(b)) = (em.get(a_id), em.get(b_id)) else {
+                continue;
+            };
+            let (Some(a_collider), Some(b_collider)) = (a.collider, b.collider) else {
+                continue;
+            };
+            let Some((a_share, b_share)) = push_shares(&a_collider, &b_collider) else {
+                continue;
+            };
+            let Some(push) = a.penetration(b) else {
+                continue;
+            };
+            if push.length() <= SWEEP_EPSILON {
+                continue;
+            }
+
+            if let Some(a) = em.get_mut(a_id) {
+                shove(a, push * a_share);
+            }
+            if let Some(b) = em.get_mut(b_id) {
+                shove(b, -push * b_share);
+            }
+        }
+    }
+}
+
+fn shove(entity: &mut Entity, push: Vec2) {
+    if push == Vec2::ZERO {
+        return;
+    }
+    entity.offset(push);
+    entity.platformer.grounded |= push.y < 0.0;
+
+    // Stop running into whatever shoved us
+    let normal = push.normalize();
+    let velocity = entity.velocity();
+    let into = velocity.dot(normal);
+    if into < 0.0 {
+        *entity.mut_velocity() = velocity - normal * into;
+    }
+}
+
+/// Keeps shoved entities from ending up inside the map
+fn settle_against_map(em: &mut EntityManager, map: &GeometryMap) {
+    for entity in em.iter_mut() {
+        let Some(collider) = entity.collider.filter(|c| !c.immovable) else {
+            continue;
+        };
+        let blockers = map.inner.iter()
+            .filter(|g| collider.is_blocked_by(&g.collider))
+            .map(Geometry::shape)
+            .collect::<Vec<_>>();
+        let settled = depenetrate(entity, &blockers);
+        entity.platformer.grounded |= settled.grounded;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_map() -> GeometryMap {
+        GeometryMap { inner: vec![], settings: MapSettings::default() }
+    }
+
+    fn mover(x: f32, velocity: Vec2) -> Entity {
+        let mut entity = Entity::bare(Rect::new(x, 0.0, 10.0, 10.0))
+            .with_controller(InputControl::default())
+            .with_collider(Collider::default());
+        entity.velocity = velocity;
+        entity
+    }
+
+    #[test]
+    fn push_shares_split_by_mass() {
+        let (light, heavy) = (Collider::default(), Collider::default().with_mass(3.0));
+        assert_eq!(push_shares(&light, &heavy), Some((0.75, 0.25)));
+        assert_eq!(push_shares(&light, &light.immovable()), Some((1.0, 0.0)));
+        assert_eq!(push_shares(&light.immovable(), &heavy.immovable()), None);
+        // Not blocking each other at all
+        let ghost = Collider::new(CollisionLayers::NPC, CollisionLayers::NONE);
+        assert_eq!(push_shares(&ghost, &Collider::new(CollisionLayers::PLAYER, CollisionLayers::NONE)), None);
+    }
+
+    #[test]
+    fn heavier_entity_gets_shoved_less() {
+        let mut em = EntityManager::new(mover(0.0, Vec2::ZERO));
+        let heavy = em.spawn(Entity::bare(Rect::new(6.0, 0.0, 10.0, 10.0)).with_collider(Collider::default().with_mass(3.0)));
+        CollisionWorld::new().step(&mut em, &empty_map(), 1.0 / 60.0);
+
+        let (player, heavy) = (em.ref_player().boundary, em.get(heavy).unwrap().boundary);
+        assert!(player.right() <= heavy.left() + SWEEP_EPSILON);
+        assert!((player.x + 3.0).abs() < 0.01 && (heavy.x - 7.0).abs() < 0.01, "{player:?} {heavy:?}");
+    }
+
+    #[test]
+    fn landing_in_an_early_substep_stays_grounded() {
+        let mut em = EntityManager::new(mover(0.0, vec2(0.0, 600.0)));
+        let map = GeometryMap {
+            inner: vec![Geometry::new_rect(-50.0, 20.0, 100.0, 10.0, None, Collider::default())],
+            settings: MapSettings::default(),
+        };
+        // A slow frame, split into substeps most of which happen after landing
+        CollisionWorld::new().step(&mut em, &map, 0.1);
+
+        let player = em.ref_player();
+        assert!((player.boundary.bottom() - 20.0).abs() <= SWEEP_EPSILON, "{:?}", player.boundary);
+        assert!(player.platformer.grounded);
+    }
+
+    #[test]
+    fn immovable_entity_stops_mover() {
+        let mut em = EntityManager::new(mover(0.0, vec2(600.0, 0.0)));
+        let post = em.spawn(Entity::bare(Rect::new(12.0, 0.0, 4.0, 10.0)).with_collider(Collider::default().immovable()));
+        CollisionWorld::new().step(&mut em, &empty_map(), 1.0 / 60.0);
+
+        assert_eq!(em.get(post).unwrap().boundary.x, 12.0);
+        assert!((em.ref_player().boundary.right() - 12.0).abs() <= SWEEP_EPSILON);
+        assert_eq!(em.ref_player().velocity.x, 0.0);
+    }
+
+    #[test]
+    fn contact_and_trigger_events() {
+        let mut em = EntityManager::new(mover(0.0, vec2(300.0, 0.0)));
+        let player = em.player_id();
+        let map = GeometryMap {
+            inner: vec![
+                Geometry::new_rect(20.0, 0.0, 10.0, 10.0, None, Collider::default()),
+                Geometry::new_rect(5.0, 0.0, 4.0, 10.0, None, Collider::trigger(CollisionLayers::WORLD, CollisionLayers::ALL)),
+            ],
+            settings: MapSettings::default(),
+        };
+        let mut world = CollisionWorld::new();
+
+        // Into the trigger, not yet at the wall
+        world.step(&mut em, &map, 1.0 / 60.0);
+        let events = world.drain_events().collect::<Vec<_>>();
+        assert!(matches!(events[..], [CollisionEvent::Trigger(TriggerEvent { trigger: ColliderRef::Geometry(1), kind: OverlapKind::Enter, .. })]), "{events:?}");
+
+        // Up against the wall and out of the trigger
+        world.step(&mut em, &map, 1.0 / 60.0);
+        let events = world.drain_events().collect::<Vec<_>>();
+        assert!(events.iter().any(|e| matches!(e, CollisionEvent::Contact { entity, other: ColliderRef::Geometry(0), normal } if *entity == player && normal.x < 0.0)), "{events:?}");
+        assert!(events.iter().any(|e| matches!(e, CollisionEvent::Trigger(TriggerEvent { kind: OverlapKind::Exit, .. }))), "{events:?}");
+
+        // Walking back away
+        em.mut_player().velocity = vec2(-300.0, 0.0);
+        world.step(&mut em, &map, 1.0 / 60.0);
+        world.step(&mut em, &map, 1.0 / 60.0);
+        let events = world.drain_events().collect::<Vec<_>>();
+        assert!(events.iter().any(|e| matches!(e, CollisionEvent::Separated { other: ColliderRef::Geometry(0), .. })), "{events:?}");
+        assert_eq!(world.contacts().count(), 0);
+    }
+}
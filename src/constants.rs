@@ -5,14 +5,64 @@ use TextureType::*;
 pub const LOGICAL_WIDTH: f32 = 1280.0;
 pub const LOGICAL_HEIGHT: f32 = 720.0;
 
-pub const PLAYER_SIZE: Vec2 = vec2(128.0, 128.0);
-pub const PLAYER_SPRITE_PATH: &str = "assets/sprites/test_sprite2.png";
 
-pub const DEFAULT_FONT_TTF_PATH: &str = "./assets/fonts/IM_Fell_English/IMFellEnglish-Regular.ttf";
+/// Asset roots in search order, see [`AssetResolver`]. Every other asset path in the game is relative to one of these.
+pub const ASSET_ROOTS: &[&str] = &["./mods", "./assets"];
+
+pub const DEFAULT_FONT_TTF_PATH: &str = "fonts/IM_Fell_English/IMFellEnglish-Regular.ttf";
+/// For italic dialogue markup, see [`TextStyle`]
+pub const DEFAULT_ITALIC_FONT_TTF_PATH: &str = "fonts/IM_Fell_English/IMFellEnglish-Italic.ttf";
 pub const DEFAULT_FONT_SIZE: u16 = 50;
 pub const DEFAULT_FONT_COLOR: Color = BLACK;
 
-pub const ROOT_TEXTURES_PATH: &str = "./assets/textures";
+/// String tables live here, see [`crate::localization`]
+pub const LANG_PATH: &str = "lang";
+/// Language every other one falls back to for strings it's missing, and the one started in unless `GAME_LANG` says otherwise
+pub const DEFAULT_LANGUAGE: &str = "en";
+/// Languages [`LANGUAGE_KEY`] cycles through, in order
+pub const LANGUAGES: &[&str] = &["en", "es"];
+pub const LANGUAGE_KEY: KeyCode = KeyCode::F2;
+/// Seconds a notice stays up, see [`Game::show_notice`]
+pub const NOTICE_SECONDS: f64 = 2.0;
+
+pub const ROOT_TEXTURES_PATH: &str = "textures";
+pub const PREFABS_PATH: &str = "prefabs";
+/// Map loaded on startup, unless overridden by the `GAME_MAP` environment variable
+pub const START_MAP_PATH: &str = "maps/test_map";
+
+/// Save slots live here, relative to the working directory, see [`SaveSlots`]
+pub const SAVES_DIR: &str = "saves";
+pub const QUICKSAVE_KEY: KeyCode = KeyCode::F5;
+pub const QUICKLOAD_KEY: KeyCode = KeyCode::F9;
+pub const QUICKSAVE_SLOT: usize = 0;
+/// Items the player has are int flags under this, e.g. `item.coin = int:3`, see [`DialogueEffect::GiveItem`]
+pub const ITEM_FLAG_PREFIX: &str = "item.";
+/// Quests are string flags of their state under this, e.g. `quest.find_cat = str:active`, see [`DialogueEffect::SetQuest`]
+pub const QUEST_FLAG_PREFIX: &str = "quest.";
+/// Logs every game flag, see [`Flags`]
+pub const FLAGS_DUMP_KEY: KeyCode = KeyCode::F3;
+
+/// Rows of dialogue text are this many font sizes apart
+pub const DIALOGUE_LINE_SPACING: f32 = 1.1;
+/// Dialogue characters revealed per second, see [`DialogueManager::with_text_speed`]
+pub const DEFAULT_TEXT_SPEED: f32 = 40.0;
+/// Extra seconds revealing dialogue waits after the end of a sentence (`.`, `!`, `?`)
+pub const SENTENCE_PAUSE: f32 = 0.3;
+/// Extra seconds revealing dialogue waits after `,`, `;` or `:`
+pub const CLAUSE_PAUSE: f32 = 0.12;
+pub const DIALOGUE_ADVANCE_KEY: KeyCode = KeyCode::Enter;
+pub const DIALOGUE_CHOICE_UP_KEY: KeyCode = KeyCode::Up;
+pub const DIALOGUE_CHOICE_DOWN_KEY: KeyCode = KeyCode::Down;
+/// Walks away from a conversation, which picks up where it left off next time
+pub const DIALOGUE_LEAVE_KEY: KeyCode = KeyCode::Escape;
+/// Whoever's being talked to getting this far away ends the conversation, like [`DIALOGUE_LEAVE_KEY`]
+pub const DIALOGUE_LEAVE_DISTANCE: f32 = 200.0;
+
+pub const INTERACT_KEY: KeyCode = KeyCode::E;
+pub const DEFAULT_INTERACT_RADIUS: f32 = 32.0;
+/// Interactables count as "in front" of the player within this angle either side of the way they're facing
+pub const INTERACT_HALF_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+pub const INTERACT_HIGHLIGHT_COLOR: Color = GOLD;
 
 pub type GResult<T> = Result<T, Box<dyn Error>>;
 
@@ -0,0 +1,25 @@
+pub mod dialogue_manager;
+pub mod script;
+pub mod markup;
+pub mod layout;
+pub mod conditions;
+pub use dialogue_manager::*;
+pub use script::*;
+pub use markup::*;
+pub use layout::*;
+pub use conditions::*;
+
+use crate::prelude::*;
+
+pub type Dialogue = Arc<DialogueScript>;
+
+/// `.dlg` files are parsed as scripts with labels and choices, anything else is read linearly, see [`script`]
+pub async fn build_dialogue(assets: &AssetResolver, dialogue_path: &str) -> GResult<Dialogue> {
+    let s = assets.load_string(dialogue_path).await?;
+    let script = if dialogue_path.ends_with(".dlg") {
+        DialogueScript::parse(dialogue_path, &s)?
+    } else {
+        DialogueScript::linear(&s)
+    };
+    Ok(Arc::new(script))
+}
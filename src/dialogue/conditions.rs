@@ -0,0 +1,448 @@
+//! What dialogue scripts can check and change in the game, see [`DialogueHost`] for how.
+//!
+//! Conditions go in square brackets, e.g. `[!flag met_blacksmith, talks == 0]`. All of the comma separated clauses have to hold,
+//! `!` in front of one negating it:
+//! - `flag met_blacksmith` bool flag set to true
+//! - `flag coins >= 3` compared, unset ints counting as 0. Values are typed like in `$set` below, and can have spaces in them,
+//!   or commas and `]` too if `"quoted"`
+//! - `item key` have at least one, `item coin < 5` compared
+//! - `quest find_cat` started, whatever state it's in, `quest find_cat == done` compared
+//! - `talks > 2` conversations with this NPC before this one, `talks` on its own meaning any
+//!
+//! Comparisons are `==`, `!=`, `<`, `<=`, `>` and `>=`, only ints having an order.
+//!
+//! Effects start with `$`:
+//! - `$set met_blacksmith true` values are `true`/`false`, an int, or anything else as a string (`"quoted"` to force one).
+//!   Without a value it's set to true
+//! - `$unset met_blacksmith`
+//! - `$give coin 3` count defaults to 1, negative takes away
+//! - `$quest find_cat` starts a quest (its state being `active`), `$quest find_cat done` sets its state
+//! - `$move 400, 200` the NPC being talked to walks there (once the conversation's over) and stays, saves included
+//!
+//! Items and quests are just flags under [`ITEM_FLAG_PREFIX`] and [`QUEST_FLAG_PREFIX`], so they're saved along with the rest.
+use crate::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FlagTest {
+    /// Bool flag set to true
+    IsSet,
+    /// Set to anything
+    Exists,
+    Compare(CompareOp, FlagValue),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConditionClause {
+    Flag { key: Box<str>, test: FlagTest },
+    Talks(CompareOp, i64),
+}
+
+/// Holds if every clause does (or doesn't, for negated ones)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DialogueCondition {
+    /// Clause and whether it's negated
+    pub clauses: Vec<(bool, ConditionClause)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogueEffect {
+    /// None unsets it
+    SetFlag { key: Box<str>, value: Option<FlagValue> },
+    GiveItem { item: Box<str>, count: i64 },
+    SetQuest { quest: Box<str>, state: Box<str> },
+    /// The NPC being talked to walks here
+    Move(Vec2),
+}
+
+/// What dialogue gets to see and do in the game. [`DialogueManager::handle_dialogue`] checks conditions
+/// and runs effects through one as soon as the script reaches them, so later conditions see what earlier effects did.
+pub trait DialogueHost {
+    fn flags(&self) -> &Flags;
+    /// `speaker` is whoever's being talked to
+    fn run_effect(&mut self, speaker: EntityId, effect: &DialogueEffect);
+}
+
+/// The game's side of dialogue: its flags (and items and quests with them) and entities.
+/// Built from [`Game`]'s fields so the [`DialogueManager`] can be borrowed alongside it.
+pub struct GameDialogueHost<'a> {
+    pub flags: &'a mut Flags,
+    pub em: &'a mut EntityManager,
+}
+
+impl DialogueHost for GameDialogueHost<'_> {
+    fn flags(&self) -> &Flags {
+        self.flags
+    }
+
+    fn run_effect(&mut self, speaker: EntityId, effect: &DialogueEffect) {
+        dlog!(Level::Debug, "Dialogue effect {:?} from {:?}", effect, speaker);
+        match effect {
+            DialogueEffect::SetFlag { key, value: Some(value) } => self.flags.set(key, value.clone()),
+            DialogueEffect::SetFlag { key, value: None } => self.flags.remove(key),
+            DialogueEffect::GiveItem { item, count } => {
+                self.flags.add_int(&format!("{ITEM_FLAG_PREFIX}{item}"), *count);
+            }
+            DialogueEffect::SetQuest { quest, state } => self.flags.set(&format!("{QUEST_FLAG_PREFIX}{quest}"), &**state),
+            DialogueEffect::Move(destination) => {
+                let Some(entity) = self.em.get_mut(speaker) else {
+                    dlog!(Level::Warn, "Dialogue tried to move {:?}, which is gone", speaker);
+                    return;
+                };
+                // Saved along with the entity, see EntitySave::destination
+                entity.go_to(*destination);
+            }
+        }
+    }
+}
+
+impl CompareOp {
+    pub fn compare<T: PartialOrd>(self, a: T, b: T) -> bool {
+        match self {
+            CompareOp::Eq => a == b,
+            CompareOp::Ne => a != b,
+            CompareOp::Lt => a < b,
+            CompareOp::Le => a <= b,
+            CompareOp::Gt => a > b,
+            CompareOp::Ge => a >= b,
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        !matches!(self, CompareOp::Eq | CompareOp::Ne)
+    }
+}
+
+impl FlagTest {
+    fn check(&self, flags: &Flags, key: &str) -> bool {
+        match self {
+            FlagTest::IsSet => flags.is_set(key),
+            FlagTest::Exists => flags.contains(key),
+            FlagTest::Compare(op, FlagValue::Int(n)) => op.compare(flags.get_int(key).unwrap_or(0), *n),
+            // Only == and != get this far, see DialogueCondition's FromStr impl
+            FlagTest::Compare(op, value) => op.compare(flags.get(key) == Some(value), true),
+        }
+    }
+}
+
+impl DialogueCondition {
+    /// `talks` is how many conversations there were with the NPC before this one
+    pub fn check(&self, flags: &Flags, talks: u32) -> bool {
+        self.clauses.iter().all(|(negated, clause)| {
+            let holds = match clause {
+                ConditionClause::Flag { key, test } => test.check(flags, key),
+                ConditionClause::Talks(op, n) => op.compare(talks as i64, *n),
+            };
+            holds != *negated
+        })
+    }
+}
+
+/// Parses the inside of the brackets, e.g. `flag coins >= 3, !talks == 0`
+impl std::str::FromStr for DialogueCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut clauses = vec![];
+        for clause in split_unquoted(s, ',') {
+            let clause = clause.trim();
+            let (negated, clause) = match clause.strip_prefix('!') {
+                Some(rest) => (true, rest.trim_start()),
+                None => (false, clause),
+            };
+            let (name, rest) = next_word(clause);
+            let (subject, rest) = next_word(rest);
+            // The value's everything after the operator, like in `$set`
+            let compare = |rest: &str| -> Result<Option<(CompareOp, FlagValue)>, String> {
+                if rest.is_empty() {
+                    return Ok(None);
+                }
+                let (op, value) = next_word(rest);
+                if value.is_empty() {
+                    return Err(format!("Bad comparison '{rest}', expected e.g. '>= 3'"));
+                }
+                let op = op.parse::<CompareOp>()?;
+                let value = parse_loose_value(value);
+                if op.is_ordering() && !matches!(value, FlagValue::Int(_)) {
+                    return Err(format!("Only ints can be compared with '{op}', not '{value}'"));
+                }
+                Ok(Some((op, value)))
+            };
+
+            let clause = match (name, subject) {
+                ("flag", key) if !key.is_empty() => ConditionClause::Flag {
+                    key: parse_key(key)?,
+                    test: compare(rest)?.map_or(FlagTest::IsSet, |(op, value)| FlagTest::Compare(op, value)),
+                },
+                ("item", item) if !item.is_empty() => ConditionClause::Flag {
+                    key: parse_key(&format!("{ITEM_FLAG_PREFIX}{item}"))?,
+                    test: FlagTest::Compare(CompareOp::Ge, FlagValue::Int(1)),
+                }.with_compare(compare(rest)?),
+                ("quest", quest) if !quest.is_empty() => ConditionClause::Flag {
+                    key: parse_key(&format!("{QUEST_FLAG_PREFIX}{quest}"))?,
+                    test: FlagTest::Exists,
+                }.with_compare(compare(rest)?),
+                ("talks", "") => ConditionClause::Talks(CompareOp::Gt, 0),
+                ("talks", op) => ConditionClause::Talks(
+                    op.parse()?,
+                    rest.parse().map_err(|e| format!("Bad talk count '{rest}': {e}"))?,
+                ),
+                _ => return Err(format!(
+                    "Bad condition '{clause}'. Valid conditions: flag <key> [op value] | item <item> [op count] | quest <quest> [op state] | talks [op count]"
+                )),
+            };
+            clauses.push((negated, clause));
+        }
+        Ok(DialogueCondition { clauses })
+    }
+}
+
+impl ConditionClause {
+    /// Swaps a flag clause's default test for a comparison, if given one
+    fn with_compare(self, compare: Option<(CompareOp, FlagValue)>) -> Self {
+        match (self, compare) {
+            (ConditionClause::Flag { key, .. }, Some((op, value))) => ConditionClause::Flag { key, test: FlagTest::Compare(op, value) },
+            (clause, _) => clause,
+        }
+    }
+}
+
+/// Parses the part after the `$`, e.g. `give coin 3`
+impl std::str::FromStr for DialogueEffect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, args) = s.trim().split_once(char::is_whitespace).unwrap_or((s.trim(), ""));
+        let args = args.trim();
+        let parts = args.split_whitespace().collect::<Vec<_>>();
+        Ok(match (name, parts.as_slice()) {
+            ("set", [key]) => DialogueEffect::SetFlag { key: parse_key(key)?, value: Some(FlagValue::Bool(true)) },
+            ("set", [key, ..]) => {
+                let value = args[key.len()..].trim();
+                DialogueEffect::SetFlag { key: parse_key(key)?, value: Some(parse_loose_value(value)) }
+            }
+            ("unset", [key]) => DialogueEffect::SetFlag { key: parse_key(key)?, value: None },
+            ("give", [item]) => DialogueEffect::GiveItem { item: parse_item_or_quest(ITEM_FLAG_PREFIX, item)?, count: 1 },
+            ("give", [item, count]) => DialogueEffect::GiveItem {
+                item: parse_item_or_quest(ITEM_FLAG_PREFIX, item)?,
+                count: count.parse().map_err(|e| format!("Bad item count '{count}': {e}"))?,
+            },
+            ("quest", [quest]) => DialogueEffect::SetQuest { quest: parse_item_or_quest(QUEST_FLAG_PREFIX, quest)?, state: Box::from("active") },
+            ("quest", [quest, state]) => DialogueEffect::SetQuest { quest: parse_item_or_quest(QUEST_FLAG_PREFIX, quest)?, state: Box::from(*state) },
+            ("move", _) => match crate::kv_file::parse_floats(args).as_deref() {
+                Some(&[x, y]) => DialogueEffect::Move(vec2(x, y)),
+                _ => return Err(format!("Bad position '{args}' for '$move', expected 'x, y'")),
+            },
+            _ => return Err(format!(
+                "Bad effect '${}'. Valid effects: $set <key> [value] | $unset <key> | $give <item> [count] | $quest <quest> [state] | $move <x, y>",
+                s.trim()
+            )),
+        })
+    }
+}
+
+impl std::str::FromStr for CompareOp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "==" => Ok(CompareOp::Eq),
+            "!=" => Ok(CompareOp::Ne),
+            "<" => Ok(CompareOp::Lt),
+            "<=" => Ok(CompareOp::Le),
+            ">" => Ok(CompareOp::Gt),
+            ">=" => Ok(CompareOp::Ge),
+            _ => Err(format!("Unknown comparison '{s}'. Valid comparisons: == | != | < | <= | > | >=")),
+        }
+    }
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        })
+    }
+}
+
+fn parse_key(key: &str) -> Result<Box<str>, String> {
+    if Flags::is_valid_key(key) {
+        Ok(Box::from(key))
+    } else {
+        Err(format!("Bad flag key '{key}', stick to letters, digits and '_ . : -'"))
+    }
+}
+
+/// Checks `name` makes a valid key under `prefix`, but gives back just the name
+fn parse_item_or_quest(prefix: &str, name: &str) -> Result<Box<str>, String> {
+    parse_key(&format!("{prefix}{name}"))?;
+    Ok(Box::from(name))
+}
+
+/// First whitespace separated word of `s` and what's after it, both trimmed
+fn next_word(s: &str) -> (&str, &str) {
+    let s = s.trim();
+    let (word, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+    (word, rest.trim())
+}
+
+/// Byte index of the first `c` in `s` that isn't inside a `"quoted"` value
+pub fn find_unquoted(s: &str, c: char) -> Option<usize> {
+    let mut quoted = false;
+    s.char_indices().find_map(|(i, ch)| {
+        if ch == '"' {
+            quoted = !quoted;
+        }
+        (ch == c && !quoted).then_some(i)
+    })
+}
+
+/// `s` split on every `c` outside of `"quoted"` values
+fn split_unquoted(s: &str, c: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut rest = s;
+    while let Some(i) = find_unquoted(rest, c) {
+        parts.push(&rest[..i]);
+        rest = &rest[i + c.len_utf8()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+/// `true`/`false`, an int, or anything else as a string, `"quoted"` to force one
+fn parse_loose_value(s: &str) -> FlagValue {
+    if let Some(quoted) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        return FlagValue::from(quoted);
+    }
+    if let Ok(b) = s.parse::<bool>() {
+        return FlagValue::Bool(b);
+    }
+    if let Ok(n) = s.parse::<i64>() {
+        return FlagValue::Int(n);
+    }
+    FlagValue::from(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(s: &str) -> DialogueCondition {
+        s.parse().unwrap()
+    }
+
+    fn effect(s: &str) -> DialogueEffect {
+        s.parse().unwrap()
+    }
+
+    fn flag(key: &str, test: FlagTest) -> ConditionClause {
+        ConditionClause::Flag { key: Box::from(key), test }
+    }
+
+    #[test]
+    fn conditions() {
+        assert_eq!(condition("!flag met_blacksmith, talks == 0").clauses, [
+            (true, flag("met_blacksmith", FlagTest::IsSet)),
+            (false, ConditionClause::Talks(CompareOp::Eq, 0)),
+        ]);
+        assert_eq!(condition("flag coins >= 3, flag name == \"3\"").clauses, [
+            (false, flag("coins", FlagTest::Compare(CompareOp::Ge, FlagValue::Int(3)))),
+            (false, flag("name", FlagTest::Compare(CompareOp::Eq, FlagValue::from("3")))),
+        ]);
+        assert_eq!(condition("item key, ! item coin < 5, talks").clauses, [
+            (false, flag("item.key", FlagTest::Compare(CompareOp::Ge, FlagValue::Int(1)))),
+            (true, flag("item.coin", FlagTest::Compare(CompareOp::Lt, FlagValue::Int(5)))),
+            (false, ConditionClause::Talks(CompareOp::Gt, 0)),
+        ]);
+        assert_eq!(condition("quest find_cat, quest find_cat != done").clauses, [
+            (false, flag("quest.find_cat", FlagTest::Exists)),
+            (false, flag("quest.find_cat", FlagTest::Compare(CompareOp::Ne, FlagValue::from("done")))),
+        ]);
+    }
+
+    #[test]
+    fn condition_errors() {
+        assert!("flag".parse::<DialogueCondition>().unwrap_err().starts_with("Bad condition 'flag'"));
+        assert!("flag a, ".parse::<DialogueCondition>().is_err());
+        assert!("flag name < bob".parse::<DialogueCondition>().unwrap_err().contains("Only ints can be compared with '<'"));
+        assert!("flag a =~ 3".parse::<DialogueCondition>().unwrap_err().starts_with("Unknown comparison '=~'"));
+        assert!("flag a >=".parse::<DialogueCondition>().unwrap_err().starts_with("Bad comparison '>='"));
+        // Everything after the operator is the value, which can't be ordered unless it's an int
+        assert!("flag a >= 3 4".parse::<DialogueCondition>().unwrap_err().contains("not 'str:3 4'"));
+        assert!("talks > many".parse::<DialogueCondition>().unwrap_err().starts_with("Bad talk count 'many'"));
+        assert!("flag bad/key".parse::<DialogueCondition>().unwrap_err().starts_with("Bad flag key"));
+    }
+
+    #[test]
+    fn checking_conditions() {
+        let mut flags = Flags::new();
+        flags.set("met", true);
+        flags.set("item.coin", 4);
+        flags.set("quest.find_cat", "active");
+        assert!(condition("flag met, item coin < 5, quest find_cat, !quest find_cat == done").check(&flags, 0));
+        // Unset ints count as 0, unset flags aren't set
+        assert!(condition("flag gold == 0, !flag never, !item key").check(&flags, 0));
+        assert!(!condition("talks").check(&flags, 0));
+        assert!(condition("talks >= 2").check(&flags, 2));
+    }
+
+    #[test]
+    fn values_set_by_effects_can_be_compared() {
+        for value in ["the  mill burned", "\"the mill, burned]\"", "\"42\"", "-7"] {
+            let DialogueEffect::SetFlag { key, value: Some(set) } = effect(&format!("set rumor {value}")) else { panic!() };
+            // Through a save and back
+            let set = set.to_string().parse::<FlagValue>().unwrap();
+            let mut flags = Flags::new();
+            flags.set(&key, set);
+            let holds = condition(&format!("flag rumor == {value}, item coin < 1"));
+            assert!(holds.check(&flags, 0), "{value}");
+            assert!(!condition(&format!("flag rumor != {value}")).check(&flags, 0), "{value}");
+        }
+        assert!(!condition("flag rumor == \"42\"").check(&{ let mut flags = Flags::new(); flags.set("rumor", 42); flags }, 0));
+    }
+
+    #[test]
+    fn quoted_delimiters() {
+        assert_eq!(find_unquoted("a \"b, c\", d", ','), Some(8));
+        assert_eq!(find_unquoted("\"]\"] rest", ']'), Some(3));
+        assert_eq!(find_unquoted("\"]", ']'), None);
+        assert_eq!(split_unquoted("flag a == \"x, y\", talks", ','), ["flag a == \"x, y\"", " talks"]);
+    }
+
+    #[test]
+    fn effects() {
+        assert_eq!(effect("set met"), DialogueEffect::SetFlag { key: Box::from("met"), value: Some(FlagValue::Bool(true)) });
+        assert_eq!(effect("set rumor the  mill  burned"), DialogueEffect::SetFlag { key: Box::from("rumor"), value: Some(FlagValue::from("the  mill  burned")) });
+        assert_eq!(effect("set count -2"), DialogueEffect::SetFlag { key: Box::from("count"), value: Some(FlagValue::Int(-2)) });
+        assert_eq!(effect("set answer \"true\""), DialogueEffect::SetFlag { key: Box::from("answer"), value: Some(FlagValue::from("true")) });
+        assert_eq!(effect("unset met"), DialogueEffect::SetFlag { key: Box::from("met"), value: None });
+        assert_eq!(effect("give coin"), DialogueEffect::GiveItem { item: Box::from("coin"), count: 1 });
+        assert_eq!(effect("give coin -3"), DialogueEffect::GiveItem { item: Box::from("coin"), count: -3 });
+        assert_eq!(effect("quest find_cat"), DialogueEffect::SetQuest { quest: Box::from("find_cat"), state: Box::from("active") });
+        assert_eq!(effect("quest find_cat done"), DialogueEffect::SetQuest { quest: Box::from("find_cat"), state: Box::from("done") });
+        assert_eq!(effect("move 400, 200.5"), DialogueEffect::Move(vec2(400.0, 200.5)));
+    }
+
+    #[test]
+    fn effect_errors() {
+        assert!("dance".parse::<DialogueEffect>().unwrap_err().starts_with("Bad effect '$dance'. Valid effects:"));
+        assert!("unset a b".parse::<DialogueEffect>().is_err());
+        assert!("give coin lots".parse::<DialogueEffect>().unwrap_err().starts_with("Bad item count 'lots'"));
+        assert!("move 400".parse::<DialogueEffect>().unwrap_err().starts_with("Bad position '400'"));
+        assert!("set bad/key".parse::<DialogueEffect>().unwrap_err().starts_with("Bad flag key"));
+    }
+}
@@ -0,0 +1,693 @@
+use crate::prelude::*;
+use std::ops::Range;
+
+struct DialogueState {
+    /// As written, see [`DialogueState::localize`]
+    script: Dialogue,
+    /// `script` in the current language
+    dialogue: Dialogue,
+    /// Whoever's being talked to, as given
+    script_speaker: Speaker,
+    /// `script_speaker` in the current language
+    speaker: Speaker,
+    portraits: Arc<HashMap<Box<str>, Texture2D>>,
+    /// Node we're on, see [`DialogueScript::nodes`]. Always a line or choice (or past the end) once settled
+    index: usize,
+    /// Whether `index` needs [`DialogueState::settle`]ing, which needs a [`DialogueHost`] to hand
+    unsettled: bool,
+    eid: EntityId,
+    /// Conversations with the entity before this one, for `talks` conditions
+    talks: u32,
+    /// Indices of the choices whose conditions held, while on a choice
+    visible: Vec<usize>,
+    /// Highlighted option while on a choice, an index into `visible`
+    selected: usize,
+    /// Last line shown, kept up while a choice is on screen
+    last_line: Option<usize>,
+    /// How much of the last line is showing
+    reveal: Reveal,
+    /// Last line wrapped to fit the dialogue box, see [`DialogueState::update_layout`]
+    layout: Option<TextLayout>,
+    /// Page of `layout` showing
+    page: usize,
+}
+
+/// A line wrapped into rows and paged, and what it was fit to, so it only gets redone if that changes
+#[derive(Clone, Debug, PartialEq)]
+struct TextLayout {
+    node: usize,
+    width: f32,
+    height: f32,
+    line_height: f32,
+    rows: Vec<Range<usize>>,
+    /// Ranges of `rows` on each page, see [`paginate`]
+    pages: Vec<Range<usize>>,
+}
+
+/// Typewriter progress through a line
+#[derive(Clone, Copy, Debug, Default)]
+struct Reveal {
+    /// Bytes of the line's text showing
+    shown: usize,
+    /// Seconds until the next character shows
+    wait: f32,
+    /// From [`TextMarker::Speed`]
+    speed_scale: f32,
+    /// Index of the next of the line's markers to apply
+    next_marker: usize,
+}
+
+impl Reveal {
+    fn new() -> Self {
+        Reveal { speed_scale: 1.0, ..Default::default() }
+    }
+
+    /// Whether everything up to `until` is showing
+    fn is_done(&self, until: usize) -> bool {
+        self.shown >= until
+    }
+
+    /// Shows everything up to `until` straight away
+    fn complete(&mut self, line: &DialogueLine, until: usize) {
+        self.shown = self.shown.max(until);
+        self.wait = 0.0;
+        // Speed changes still count for the rest of the line, pauses skipped over don't
+        while let Some(&(_, marker)) = line.markers.get(self.next_marker).filter(|(at, _)| *at < self.shown) {
+            if let TextMarker::Speed(scale) = marker {
+                self.speed_scale = scale;
+            }
+            self.next_marker += 1;
+        }
+    }
+
+    /// Shows characters at `speed` per second up to `until`, see [`DialogueManager::with_text_speed`]
+    fn update(&mut self, line: &DialogueLine, until: usize, speed: f32, dt: f32) {
+        if speed <= 0.0 || !speed.is_finite() {
+            self.complete(line, until);
+            return;
+        }
+        self.wait -= dt;
+        while self.wait <= 0.0 && !self.is_done(until) {
+            // Markers sitting right here apply before the character after them shows
+            if let Some(&(at, marker)) = line.markers.get(self.next_marker).filter(|(at, _)| *at <= self.shown) {
+                debug_assert!(at == self.shown, "markers are applied in order as the text reaches them");
+                match marker {
+                    TextMarker::Pause(seconds) => self.wait += seconds,
+                    TextMarker::Speed(scale) => self.speed_scale = scale,
+                }
+                self.next_marker += 1;
+                continue;
+            }
+
+            let rest = &line.text[self.shown..];
+            let Some(c) = rest.chars().next() else {
+                break;
+            };
+            self.shown += c.len_utf8();
+            self.wait += 1.0 / (speed * self.speed_scale);
+            // Only at the end of a sentence or clause, so e.g. "3.5" or "..." don't stutter
+            if line.text[self.shown..].starts_with(char::is_whitespace) {
+                match c {
+                    '.' | '!' | '?' => self.wait += SENTENCE_PAUSE,
+                    ',' | ';' | ':' => self.wait += CLAUSE_PAUSE,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl DialogueState {
+    /// Picks up where an interrupted conversation left off, otherwise starts at the top,
+    /// or at [`REPEAT_LABEL`] if there's been a conversation before
+    fn new(talker: &Talker, eid: EntityId, loc: &Localization) -> Self {
+        let mut state = DialogueState {
+            script: Arc::clone(&talker.dialogue),
+            dialogue: Arc::clone(&talker.dialogue),
+            script_speaker: talker.speaker.clone(),
+            speaker: talker.speaker.clone(),
+            portraits: Arc::clone(&talker.portraits),
+            index: 0,
+            unsettled: true,
+            eid,
+            talks: talker.talks,
+            visible: vec![],
+            selected: 0,
+            last_line: None,
+            reveal: Reveal::new(),
+            layout: None,
+            page: 0,
+        };
+        state.localize(loc);
+        match talker.resume {
+            // The count already includes the conversation being resumed
+            Some(index) => state.resume_at(index, talker.talks.saturating_sub(1)),
+            None if talker.talks > 0 => state.index = state.dialogue.label(REPEAT_LABEL).unwrap_or(0),
+            None => (),
+        }
+        state
+    }
+
+    /// Jumps to `index`, somewhere a conversation was left off (so already settled), with `talks` conversations before it
+    fn resume_at(&mut self, index: usize, talks: u32) {
+        self.index = index;
+        self.talks = talks;
+        self.selected = 0;
+        self.reveal = Reveal::new();
+        self.page = 0;
+        // Best guess at what was on screen if we're resuming onto a choice
+        self.last_line = self.dialogue.nodes.get(..index)
+            .and_then(|nodes| nodes.iter().rposition(|node| matches!(node, DialogueNode::Line(_))));
+        // Settled again for the choices that are showing. Left off at a line or choice, settling stops right there
+        // without running anything, and left off anywhere else it never got past there, so effects still run just once
+        self.unsettled = true;
+    }
+
+    /// Puts the dialogue's text into `loc`'s language, starting the current line over as it'll be laid out differently
+    fn localize(&mut self, loc: &Localization) {
+        self.dialogue = Arc::new(self.script.localize(loc));
+        self.speaker = Speaker { name: Box::from(loc.resolve(&self.script_speaker.name)), ..self.script_speaker.clone() };
+        self.reveal = Reveal::new();
+        self.layout = None;
+        self.page = 0;
+    }
+
+    /// Follows jumps, checks conditions and runs effects until we land on something to show (or the end)
+    fn settle(&mut self, host: &mut dyn DialogueHost) {
+        self.unsettled = false;
+        // A script jumping in a circle without any lines would hang us otherwise
+        for _ in 0..=self.dialogue.len() {
+            match self.dialogue.nodes.get(self.index) {
+                Some(DialogueNode::Jump(target)) => self.index = *target,
+                Some(DialogueNode::Guard { condition, otherwise }) => {
+                    self.index = if condition.check(host.flags(), self.talks) { self.index + 1 } else { *otherwise };
+                }
+                Some(DialogueNode::Effect(effect)) => {
+                    host.run_effect(self.eid, effect);
+                    self.index += 1;
+                }
+                Some(DialogueNode::Line(_)) => {
+                    self.last_line = Some(self.index);
+                    self.reveal = Reveal::new();
+                    self.page = 0;
+                    return;
+                }
+                Some(DialogueNode::Choice(choices)) => {
+                    self.visible = (0..choices.len())
+                        .filter(|&i| choices[i].condition.as_ref().is_none_or(|c| c.check(host.flags(), self.talks)))
+                        .collect();
+                    self.selected = 0;
+                    // Nothing to pick from, so carry on as if it wasn't there
+                    if self.visible.is_empty() {
+                        self.index += 1;
+                        continue;
+                    }
+                    return;
+                }
+                None => return,
+            }
+        }
+        dlog!(Level::Error, "Dialogue jumps in a loop without any lines, ending it");
+        self.index = self.dialogue.len();
+    }
+
+    // Seperating into `is_readable` and `read`
+    // plays nicer with the borrow checker
+    #[inline]
+    pub fn is_readable(&self) -> bool {
+        self.index < self.dialogue.len()
+    }
+
+    fn line(&self) -> Option<&DialogueLine> {
+        match self.last_line.map(|i| &self.dialogue.nodes[i]) {
+            Some(DialogueNode::Line(line)) => Some(line),
+            _ => None,
+        }
+    }
+
+    /// Wraps the line on screen into rows `width` wide and pages `height` tall, unless it already is.
+    /// Rows are `line_height` apart, more for text enlarged by markup (see [`row_height`])
+    pub fn update_layout(&mut self, width: f32, height: f32, line_height: f32, measure: impl Fn(&DialogueLine, Range<usize>) -> f32) {
+        let Some(node) = self.last_line else {
+            return;
+        };
+        let up_to_date = self.layout.as_ref()
+            .is_some_and(|layout| layout.node == node && layout.width == width && layout.height == height && layout.line_height == line_height);
+        if up_to_date {
+            return;
+        }
+        let Some(line) = self.line() else {
+            return;
+        };
+        let rows = wrap_rows(&line.text, width, |range| measure(line, range));
+        let heights = rows.iter().map(|row| row_height(&line.spans, row.clone(), line_height)).collect::<Vec<_>>();
+        let pages = paginate(&heights, height);
+        self.layout = Some(TextLayout { node, width, height, line_height, rows, pages });
+        // Rewrapping (e.g. the window changed) can leave fewer pages
+        self.page = self.page.min(self.page_count() - 1);
+    }
+
+    /// Layout of the line on screen, None until it's been laid out
+    fn layout(&self) -> Option<&TextLayout> {
+        self.layout.as_ref().filter(|layout| Some(layout.node) == self.last_line)
+    }
+
+    fn page_count(&self) -> usize {
+        self.layout().map_or(1, |layout| layout.pages.len())
+    }
+
+    /// Rows of the page showing
+    pub fn page_rows(&self) -> &[Range<usize>] {
+        match self.layout() {
+            Some(layout) => layout.pages.get(self.page).map_or(&[], |page| &layout.rows[page.clone()]),
+            None => &[],
+        }
+    }
+
+    /// Byte the page showing ends at, the last page running to the end of the line
+    fn page_end(&self) -> usize {
+        let text_len = self.line().map_or(0, |line| line.text.len());
+        if self.page + 1 >= self.page_count() {
+            return text_len;
+        }
+        self.page_rows().last().map_or(text_len, |row| row.end)
+    }
+
+    /// Reveals more of the page on screen, see [`Reveal::update`]
+    pub fn update_reveal(&mut self, speed: f32, dt: f32) {
+        let until = self.page_end();
+        let DialogueState { dialogue, last_line, reveal, .. } = self;
+        if let Some(DialogueNode::Line(line)) = last_line.map(|i| &dialogue.nodes[i]) {
+            reveal.update(line, until, speed, dt);
+        }
+    }
+
+    /// Whether the line on screen is showing in full, last page and all. Choices only show up after it is
+    pub fn is_revealed(&self) -> bool {
+        self.line().is_none_or(|line| self.reveal.is_done(line.text.len()))
+    }
+
+    /// What's on screen: the current line, or the last one along with the choices that can be picked
+    pub fn read(&self) -> (Option<&DialogueLine>, Vec<&DialogueChoice>) {
+        let line = self.line();
+        match self.dialogue.nodes.get(self.index) {
+            Some(DialogueNode::Choice(choices)) => (line, self.visible.iter().map(|&i| &choices[i]).collect()),
+            _ => (line, vec![]),
+        }
+    }
+
+    pub fn choices(&self) -> usize {
+        match self.dialogue.nodes.get(self.index) {
+            Some(DialogueNode::Choice(_)) => self.visible.len(),
+            _ => 0,
+        }
+    }
+
+    /// Moves the highlighted choice by `by`, wrapping around
+    pub fn select(&mut self, by: isize) {
+        let count = self.choices();
+        if count > 0 {
+            self.selected = (self.selected as isize + by).rem_euclid(count as isize) as usize;
+        }
+    }
+
+    /// Shows the rest of the page if it's still being revealed, otherwise turns the page,
+    /// otherwise [`DialogueState::advance`]s
+    pub fn skip_or_advance(&mut self, host: &mut dyn DialogueHost) {
+        let until = self.page_end();
+        if !self.reveal.is_done(until) {
+            let DialogueState { dialogue, last_line, reveal, .. } = self;
+            if let Some(DialogueNode::Line(line)) = last_line.map(|i| &dialogue.nodes[i]) {
+                reveal.complete(line, until);
+            }
+        } else if self.page + 1 < self.page_count() {
+            self.page += 1;
+        } else {
+            self.advance(host);
+        }
+    }
+
+    /// Past the current line, or on with the highlighted choice
+    pub fn advance(&mut self, host: &mut dyn DialogueHost) {
+        self.index = match self.dialogue.nodes.get(self.index) {
+            Some(DialogueNode::Choice(choices)) => choices[self.visible[self.selected]].target.unwrap_or(self.index + 1),
+            _ => self.index + 1,
+        };
+        self.selected = 0;
+        self.settle(host);
+    }
+}
+
+/// What [`DialogueManager::handle_dialogue`] wants drawn this frame
+pub struct DialogueView<'a> {
+    /// The whole line, even what's not revealed yet
+    pub text: &'a str,
+    /// Styling of `text`, see [`MarkedText::spans`]
+    pub spans: &'a [StyledSpan],
+    /// Byte ranges of `text` on the page showing, one per row, see [`wrap_rows`]
+    pub rows: &'a [Range<usize>],
+    /// Bytes of `text` revealed so far, counting earlier pages
+    pub revealed: usize,
+    /// For the name plate
+    pub speaker: &'a str,
+    pub portrait: Option<&'a Texture2D>,
+    /// Empty unless the player's picking one
+    pub choices: Vec<&'a DialogueChoice>,
+    pub selected: usize,
+    pub text_params: Option<TextParams<'a>>,
+    /// For `{i}` markup, see [`TextStyle::apply`]
+    pub italic_font: Option<&'a Font>,
+}
+
+/// Custom subset of the macroquad [`TextParams`] for this game's usage that owns its [`Font`]
+#[derive(Clone)]
+pub struct OwnedTextParams {
+    pub font: Option<Font>,
+    /// Used for italic markup, which stays upright without one
+    pub italic_font: Option<Font>,
+    pub font_size: u16,
+    pub color: Color,
+}
+
+/// A conversation started or stopped, see [`DialogueManager::drain_events`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DialogueEvent {
+    /// A conversation with `eid` began, `resumed` if it picked up where an interrupted one left off
+    Started { eid: EntityId, resumed: bool },
+    /// Stopped before the script ended, to be picked up again at `index` next time (see [`Talker::resume`])
+    Interrupted { eid: EntityId, index: usize },
+    /// Got to the end of the script
+    Finished { eid: EntityId },
+}
+
+pub struct DialogueManager {
+    state: Option<DialogueState>,
+    text_params: Option<OwnedTextParams>,
+    /// Characters revealed per second, see [`DialogueManager::with_text_speed`]
+    text_speed: f32,
+    events: Vec<DialogueEvent>,
+}
+
+impl OwnedTextParams {
+    pub fn as_text_params(&self) -> TextParams<'_> {
+        TextParams { 
+            font: self.font.as_ref(),
+            font_size: self.font_size,
+            color: self.color,
+            ..Default::default()
+        }
+    }
+}
+
+impl DialogueManager {
+    pub fn from_text_params(text_params: OwnedTextParams) -> Self {
+        DialogueManager { state: None, text_params: Some(text_params), text_speed: DEFAULT_TEXT_SPEED, events: vec![] }
+    }
+
+    /// Characters revealed per second, before any `{speed}` markup. 0 or infinity shows lines all at once
+    pub fn with_text_speed(mut self, text_speed: f32) -> Self {
+        self.text_speed = text_speed;
+        self
+    }
+
+    pub fn set_text_speed(&mut self, text_speed: f32) {
+        self.text_speed = text_speed;
+    }
+
+    /// E.g. for another language's fonts, see [`Localization::load_text_params`]
+    pub fn set_text_params(&mut self, text_params: OwnedTextParams) {
+        self.text_params = Some(text_params);
+        // Rows measured with the old fonts won't fit anymore
+        if let Some(state) = self.state.as_mut() {
+            state.layout = None;
+            state.page = 0;
+        }
+    }
+
+    /// Puts any loaded dialogue into `loc`'s language, e.g. after switching languages
+    pub fn relocalize(&mut self, loc: &Localization) {
+        if let Some(state) = self.state.as_mut() {
+            state.localize(loc);
+        }
+    }
+
+    /// Loads an entity's dialogue if it has any ([`Talker`]) and it isn't already loaded, but does not draw it.
+    /// Use [`DialogueManager::handle_dialogue`] to draw.
+    /// Anyone else's conversation gets [`DialogueManager::interrupt`]ed.
+    pub fn load_dialogue(&mut self, entity: &Entity, loc: &Localization) {
+        let Some(talker) = entity.talker.as_ref() else {
+            return;
+        };
+        if self.loaded_eid() == Some(entity.id()) {
+            return;
+        }
+        self.interrupt();
+        self.state = Some(DialogueState::new(talker, entity.id(), loc));
+        self.events.push(DialogueEvent::Started { eid: entity.id(), resumed: talker.resume.is_some() });
+    }
+
+    /// Ends the loaded conversation early, so it picks up where it left off next time, see [`DialogueEvent::Interrupted`]
+    pub fn interrupt(&mut self) {
+        let Some(state) = self.state.take() else {
+            return;
+        };
+        self.events.push(match state.is_readable() {
+            true => DialogueEvent::Interrupted { eid: state.eid, index: state.index },
+            false => DialogueEvent::Finished { eid: state.eid },
+        });
+    }
+
+    /// Takes every event since the last drain, oldest first
+    pub fn drain_events(&mut self) -> impl Iterator<Item = DialogueEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    /// Handles dialogue input, lays out and reveals text and returns what to draw if there is dialogue to play.
+    /// Conditions and effects in the script go through `host`.
+    /// [`DIALOGUE_ADVANCE_KEY`] first shows the rest of a page being revealed, then turns the page,
+    /// then moves past the line or picks the highlighted choice. [`DIALOGUE_LEAVE_KEY`] [`DialogueManager::interrupt`]s.
+    /// Keys are only read if `has_focus`, otherwise it just carries on drawing, see [`crate::focus`].
+    pub fn handle_dialogue(&mut self, pset: &PSet, host: &mut dyn DialogueHost, has_focus: bool) -> Option<DialogueView<'_>> {
+        if self.state.is_some() && has_focus && is_key_pressed(DIALOGUE_LEAVE_KEY) {
+            self.interrupt();
+            return None;
+        }
+        // Borrowing just the field, as the state's borrowed mutably below
+        let base_params = self.text_params.as_ref().map_or(TextParams {
+            font_size: DEFAULT_FONT_SIZE,
+            color: DEFAULT_FONT_COLOR,
+            ..Default::default()
+        }, OwnedTextParams::as_text_params);
+        let italic_font = self.text_params.as_ref().and_then(|params| params.italic_font.as_ref());
+        if let Some(mut_state) = self.state.as_mut() {
+            if mut_state.unsettled {
+                mut_state.settle(host);
+            }
+            let with_portrait = mut_state.line().and_then(|line| mut_state.speaker.portrait_for(line)).is_some();
+            let area = window_drawing::dialogue_text_rect(pset, with_portrait);
+            let line_height = window_drawing::dialogue_line_height(base_params.font_size);
+            mut_state.update_layout(area.w, area.h, line_height, |line, range| {
+                window_drawing::measure_styled_text(&line.text, &line.spans, range, &base_params, italic_font)
+            });
+
+            mut_state.update_reveal(self.text_speed, get_frame_time());
+            if has_focus && mut_state.is_revealed() {
+                if is_key_pressed(DIALOGUE_CHOICE_UP_KEY) {
+                    mut_state.select(-1);
+                }
+                if is_key_pressed(DIALOGUE_CHOICE_DOWN_KEY) {
+                    mut_state.select(1);
+                }
+            }
+            if has_focus && is_key_pressed(DIALOGUE_ADVANCE_KEY) {
+                mut_state.skip_or_advance(host);
+            }
+            if mut_state.is_readable() {
+                let state = self.state.as_ref()?;
+                let (line, choices) = state.read();
+                let portrait = line
+                    .and_then(|line| state.speaker.portrait_for(line))
+                    .and_then(|path| state.portraits.get(&*path));
+                return Some(DialogueView {
+                    text: line.map_or("", |line| &line.text),
+                    spans: line.map_or(&[], |line| &line.spans),
+                    rows: state.page_rows(),
+                    revealed: state.reveal.shown,
+                    speaker: line.map_or(&state.speaker.name, |line| state.speaker.name_for(line)),
+                    portrait,
+                    choices: if state.is_revealed() { choices } else { vec![] },
+                    selected: state.selected,
+                    text_params: self.get_text_params(),
+                    italic_font: self.get_italic_font(),
+                });
+            }
+        }
+        // If here, either was already None or unreadable, so consume state to end dialogue
+        if let Some(state) = self.state.take() {
+            self.events.push(DialogueEvent::Finished { eid: state.eid });
+        }
+        None
+    }
+
+    /// Loaded dialogue's entity and how far into it we are, e.g. for saving
+    pub fn progress(&self) -> Option<(EntityId, usize)> {
+        self.state.as_ref().map(|state| (state.eid, state.index))
+    }
+
+    /// Loads an entity's dialogue picking up at `index`, e.g. from a save. Replaces whatever's loaded without any events,
+    /// as it's the same conversation carrying on
+    pub fn restore_progress(&mut self, entity: &Entity, index: usize, loc: &Localization) {
+        let Some(talker) = entity.talker.as_ref() else {
+            return;
+        };
+        let mut state = DialogueState::new(talker, entity.id(), loc);
+        // The saved count already includes this conversation
+        state.resume_at(index, talker.talks.saturating_sub(1));
+        self.state = Some(state);
+    }
+
+    /// Drops whatever dialogue is loaded, along with any events not drained yet, e.g. when loading a save
+    pub fn unload_dialogue(&mut self) {
+        self.state = None;
+        self.events.clear();
+    }
+
+    /// Id of the entity whose dialogue is loaded, if any
+    pub fn loaded_eid(&self) -> Option<EntityId> {
+        self.state.as_ref().map(|state| state.eid)
+    }
+
+    /// What the loaded dialogue wants the world doing, see [`DialogueScript::world`]
+    pub fn world_mode(&self) -> Option<WorldMode> {
+        self.state.as_ref().map(|state| state.dialogue.world)
+    }
+
+    /// Drops any loaded dialogue if it belongs to the given entity, e.g. when it's despawned
+    pub fn unload_dialogue_of(&mut self, eid: EntityId) {
+        if self.state.as_ref().is_some_and(|state| state.eid == eid) {
+            self.state = None;
+        }
+    }
+
+    // REVIEW // Unused for now
+    pub fn has_loaded_dialogue(&self) -> bool {
+        self.state.is_some()
+    }
+
+    // REVIEW // Unused for now
+    pub fn has_active_dialogue(&self) -> bool {
+        self.state.as_ref().is_some_and(|s| s.index > 0)
+    }
+
+    fn get_italic_font(&self) -> Option<&Font> {
+        self.text_params.as_ref().and_then(|params| params.italic_font.as_ref())
+    }
+
+    pub fn get_text_params(&self) -> Option<TextParams<'_>> {
+        self.text_params.as_ref().map(OwnedTextParams::as_text_params)
+    } 
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(raw: &str) -> DialogueLine {
+        let MarkedText { text, markers, spans } = MarkedText::parse(raw).unwrap();
+        DialogueLine { text, markers, spans, ..Default::default() }
+    }
+
+    /// How much is showing after each of `steps` seconds, revealing 10 characters a second
+    fn reveal(line: &DialogueLine, steps: &[f32]) -> Vec<usize> {
+        let mut reveal = Reveal::new();
+        steps.iter().map(|&dt| {
+            reveal.update(line, line.text.len(), 10.0, dt);
+            reveal.shown
+        }).collect()
+    }
+
+    #[test]
+    fn reveals_at_speed() {
+        assert_eq!(reveal(&line("abcdef"), &[0.0, 0.1, 0.25, 10.0]), [1, 2, 4, 6]);
+    }
+
+    #[test]
+    fn punctuation_pauses_only_at_the_end_of_a_clause() {
+        // The '.' before a space waits SENTENCE_PAUSE on top, the one in "3.5" doesn't
+        let sentence = line("a. b");
+        assert_eq!(reveal(&sentence, &[0.0, 0.1, 0.1, SENTENCE_PAUSE]), [1, 2, 2, 3]);
+        assert_eq!(reveal(&line("3.5 x"), &[0.0, 0.1, 0.1]), [1, 2, 3]);
+    }
+
+    #[test]
+    fn markers_apply_as_they_are_reached() {
+        let paused = line("ab{pause 1}cd");
+        assert_eq!(reveal(&paused, &[0.0, 0.1, 0.5, 0.6]), [1, 2, 2, 3]);
+        let slowed = line("a{speed 0.5}bcd");
+        assert_eq!(reveal(&slowed, &[0.0, 0.1, 0.1, 0.1]), [1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn completing_skips_pauses_but_keeps_speed() {
+        let text = line("ab{pause 5}{speed 0.5}cd{pause 5}ef");
+        let mut reveal = Reveal::new();
+        reveal.complete(&text, 2);
+        assert_eq!((reveal.shown, reveal.speed_scale, reveal.next_marker), (2, 1.0, 0));
+        reveal.complete(&text, 4);
+        assert_eq!((reveal.shown, reveal.speed_scale, reveal.next_marker), (4, 0.5, 2));
+        reveal.update(&text, text.text.len(), 10.0, 0.0);
+        // The pause at 4 hasn't been passed yet, so it still counts
+        assert_eq!(reveal.shown, 4);
+    }
+
+    /// Counts effects rather than running them
+    #[derive(Default)]
+    struct Host {
+        flags: Flags,
+        effects: Vec<DialogueEffect>,
+    }
+
+    impl DialogueHost for Host {
+        fn flags(&self) -> &Flags {
+            &self.flags
+        }
+
+        fn run_effect(&mut self, _speaker: EntityId, effect: &DialogueEffect) {
+            self.effects.push(effect.clone());
+        }
+    }
+
+    fn talker(script: &str, talks: u32, resume: Option<usize>) -> Talker {
+        Talker {
+            dialogue: Arc::new(DialogueScript::parse("test.dlg", script).unwrap()),
+            speaker: Speaker::default(),
+            portraits: Arc::default(),
+            talks,
+            resume,
+        }
+    }
+
+    #[test]
+    fn resuming_runs_no_effect_twice() {
+        let script = "$give coin\nHi\n$give coin 2\n* A\n* [talks] B\n";
+        let mut host = Host::default();
+        let mut state = DialogueState::new(&talker(script, 1, None), EntityId::UNSPAWNED, &Localization::default());
+        state.settle(&mut host);
+        state.advance(&mut host);
+        assert_eq!((state.index, host.effects.len()), (3, 2));
+
+        // Left off at the choice, talked to once before this
+        let mut resumed = DialogueState::new(&talker(script, 2, Some(state.index)), EntityId::UNSPAWNED, &Localization::default());
+        assert!(resumed.unsettled);
+        resumed.settle(&mut host);
+        assert_eq!(host.effects.len(), 2);
+        assert_eq!((resumed.index, resumed.last_line, resumed.choices()), (3, Some(1), 2));
+
+        // Left off before it ever settled, so the first effect hasn't run yet
+        let mut host = Host::default();
+        let mut resumed = DialogueState::new(&talker(script, 1, Some(0)), EntityId::UNSPAWNED, &Localization::default());
+        resumed.settle(&mut host);
+        assert_eq!(host.effects, [DialogueEffect::GiveItem { item: Box::from("coin"), count: 1 }]);
+        assert_eq!(resumed.index, 1);
+    }
+}
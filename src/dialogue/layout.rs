@@ -0,0 +1,154 @@
+//! Fitting dialogue text into the dialogue box: word wrapping into rows, and rows into pages.
+use crate::prelude::*;
+use std::ops::Range;
+
+/// Splits `text` into rows no wider than `max_width`, breaking between words, as byte ranges into `text`.
+/// `measure` gives the width of a range of `text`, which can be styled however.
+/// Words too wide for a row on their own get broken between characters.
+/// Spaces at a break belong to neither row. Always gives at least one row, empty for empty text.
+pub fn wrap_rows(text: &str, max_width: f32, measure: impl Fn(Range<usize>) -> f32) -> Vec<Range<usize>> {
+    let mut rows = vec![];
+    let mut row: Option<Range<usize>> = None;
+
+    for word in words(text) {
+        match row.as_mut() {
+            Some(current) if measure(current.start..word.end) <= max_width => current.end = word.end,
+            _ => {
+                rows.extend(row.take());
+                if measure(word.clone()) <= max_width {
+                    row = Some(word);
+                    continue;
+                }
+                // Too wide to fit anywhere, split it up and carry on after the last piece
+                let mut pieces = break_word(text, word, max_width, &measure);
+                row = pieces.pop();
+                rows.extend(pieces);
+            }
+        }
+    }
+    rows.extend(row);
+
+    if rows.is_empty() {
+        rows.push(0..0);
+    }
+    rows
+}
+
+/// Byte ranges of every run of non-whitespace
+fn words(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    text.split_whitespace().map(move |word| {
+        let start = word.as_ptr() as usize - text.as_ptr() as usize;
+        start..start + word.len()
+    })
+}
+
+/// Splits a word into pieces no wider than `max_width`, at least one character each
+fn break_word(text: &str, word: Range<usize>, max_width: f32, measure: impl Fn(Range<usize>) -> f32) -> Vec<Range<usize>> {
+    let mut pieces = vec![];
+    let mut start = word.start;
+    let mut end = word.start;
+    for (i, c) in text[word.clone()].char_indices() {
+        let next = word.start + i + c.len_utf8();
+        if end > start && measure(start..next) > max_width {
+            pieces.push(start..end);
+            start = end;
+        }
+        end = next;
+    }
+    pieces.push(start..end);
+    pieces
+}
+
+/// Rows of each choice's text wrapped to `max_width` (see [`wrap_rows`]), choices being unstyled so `measure` takes the text itself
+pub fn wrap_choices<'a>(choices: &[&'a str], max_width: f32, measure: impl Fn(&str) -> f32) -> Vec<Vec<&'a str>> {
+    choices.iter()
+        .map(|&text| wrap_rows(text, max_width, |range| measure(&text[range])).into_iter().map(|range| &text[range]).collect())
+        .collect()
+}
+
+/// Height a row of `spans`-styled text takes up: `line_height` scaled by the biggest `{size}` in it
+pub fn row_height(spans: &[StyledSpan], row: Range<usize>, line_height: f32) -> f32 {
+    let scale = styled_pieces(spans, row).map(|(_, style)| style.size).fold(0.0, f32::max);
+    // Nothing in the row to size it, e.g. an empty line
+    if scale > 0.0 { line_height * scale } else { line_height }
+}
+
+/// Groups rows into pages no taller than `page_height`, given each row's height (see [`row_height`]), as ranges of row indices.
+/// Every page gets at least one row, even if it's too tall on its own, so there's always somewhere to put text
+pub fn paginate(row_heights: &[f32], page_height: f32) -> Vec<Range<usize>> {
+    let mut pages = vec![];
+    let mut start = 0;
+    let mut height = 0.0;
+    for (i, &row) in row_heights.iter().enumerate() {
+        if i > start && height + row > page_height {
+            pages.push(start..i);
+            start = i;
+            height = 0.0;
+        }
+        height += row;
+    }
+    pages.push(start..row_heights.len());
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every character one unit wide
+    fn wrap(text: &str, max_width: f32) -> Vec<&str> {
+        wrap_rows(text, max_width, |range| text[range].chars().count() as f32)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn breaks_between_words() {
+        assert_eq!(wrap("the quick brown fox", 10.0), ["the quick", "brown fox"]);
+        assert_eq!(wrap("the quick brown fox", 19.0), ["the quick brown fox"]);
+        // Runs of whitespace at a break go with neither row
+        assert_eq!(wrap("aaaa   bbbb\ncccc", 6.0), ["aaaa", "bbbb", "cccc"]);
+    }
+
+    #[test]
+    fn breaks_up_words_too_long_for_a_row() {
+        assert_eq!(wrap("ab abcdefghij k", 4.0), ["ab", "abcd", "efgh", "ij k"]);
+        // Even if not a single character fits
+        assert_eq!(wrap("abc", 0.5), ["a", "b", "c"]);
+        assert_eq!(wrap("héllo wörld", 3.0), ["hél", "lo", "wör", "ld"]);
+    }
+
+    #[test]
+    fn always_at_least_one_row() {
+        assert_eq!(wrap_rows("", 10.0, |_| 0.0), vec![0..0]);
+        assert_eq!(wrap_rows("   ", 10.0, |_| 0.0), vec![0..0]);
+    }
+
+    #[test]
+    fn pages() {
+        assert_eq!(paginate(&[30.0; 7], 100.0), [0..3, 3..6, 6..7]);
+        assert_eq!(paginate(&[30.0; 3], 90.0), vec![0..3]);
+        // A row too tall for any page still gets one to itself
+        assert_eq!(paginate(&[30.0, 200.0, 30.0], 100.0), [0..1, 1..2, 2..3]);
+        assert_eq!(paginate(&[], 100.0), vec![0..0]);
+    }
+
+    #[test]
+    fn enlarged_text_takes_up_more_of_the_page() {
+        let line = MarkedText::parse("small {size 2}big{/size} small {size 0.5}tiny").unwrap();
+        let rows = wrap_rows(&line.text, 9.0, |range| line.text[range].chars().count() as f32);
+        assert_eq!(rows.iter().map(|row| &line.text[row.clone()]).collect::<Vec<_>>(), ["small big", "small", "tiny"]);
+        let heights = rows.iter().map(|row| row_height(&line.spans, row.clone(), 30.0)).collect::<Vec<_>>();
+        assert_eq!(heights, [60.0, 30.0, 15.0]);
+        // Three rows at the usual height would've fit
+        assert_eq!(paginate(&heights, 90.0), [0..2, 2..3]);
+        assert_eq!(row_height(&[], 0..0, 30.0), 30.0);
+    }
+
+    #[test]
+    fn long_choices_wrap() {
+        let wrapped = wrap_choices(&["Yes", "Looking for work, if you have any"], 12.0, |text| text.chars().count() as f32);
+        assert_eq!(wrapped, [vec!["Yes"], vec!["Looking for", "work, if you", "have any"]]);
+    }
+}
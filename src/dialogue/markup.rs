@@ -0,0 +1,295 @@
+//! Inline markup in dialogue text, as `{tag value}` tags that get stripped out of the text shown:
+//! ```text
+//! Well...{pause 0.5} I suppose.
+//! {speed 0.5}Slowly now,{speed} back to normal.
+//! That's {color red}{i}not{/i}{/color} what I said. {size 1.5}{shake}RUN!{/shake}{/size}
+//! Braces are written {{like this}.
+//! ```
+//! Timing:
+//! - `{pause seconds}` stops revealing for a while
+//! - `{speed multiplier}` scales the reveal speed from there on, `{speed}` on its own resets it
+//!
+//! Styling, each lasting until its closing tag (e.g. `{/color}`) or the end of the line, and nesting with others:
+//! - `{color name}` (see [`parse_color`]) or `{color #rrggbb}`
+//! - `{i}` italics
+//! - `{size multiplier}` relative to the usual font size
+//! - `{shake}` and `{wave}`, see [`TextStyle`]
+//!
+//! `{{` is a literal `{`.
+use crate::prelude::*;
+use std::ops::Range;
+
+/// Something that happens partway through a line, see [`MarkedText::markers`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextMarker {
+    /// Seconds to wait before revealing any further
+    Pause(f32),
+    /// Reveal speed multiplier from here on
+    Speed(f32),
+}
+
+/// How a stretch of text is drawn, on top of the dialogue's usual [`TextParams`] (see [`TextStyle::apply`])
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    /// None for the usual color
+    pub color: Option<Color>,
+    pub italic: bool,
+    /// Font size multiplier
+    pub size: f32,
+    /// Each character jitters about
+    pub shake: bool,
+    /// Characters bob up and down one after the other
+    pub wave: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StyledSpan {
+    /// Byte range into [`MarkedText::text`]
+    pub range: Range<usize>,
+    pub style: TextStyle,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MarkedText {
+    /// With the tags stripped out
+    pub text: Box<str>,
+    /// Byte offset into `text` each marker sits at, in order
+    pub markers: Vec<(usize, TextMarker)>,
+    /// Covering all of `text` in order without gaps, so unstyled text gets a (default) span too
+    pub spans: Vec<StyledSpan>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle { color: None, italic: false, size: 1.0, shake: false, wave: false }
+    }
+}
+
+impl TextStyle {
+    pub fn is_animated(&self) -> bool {
+        self.shake || self.wave
+    }
+
+    /// `base` with this style on top. Italics need `italic_font`, and stay upright without one
+    pub fn apply<'a>(&self, base: &TextParams<'a>, italic_font: Option<&'a Font>) -> TextParams<'a> {
+        let mut params = base.clone();
+        if let Some(color) = self.color {
+            params.color = color;
+        }
+        if self.italic && italic_font.is_some() {
+            params.font = italic_font;
+        }
+        params.font_size = (base.font_size as f32 * self.size).round().max(1.0) as u16;
+        params
+    }
+}
+
+/// Open styling tags, innermost last, so closing one puts back whatever it covered up
+#[derive(Default)]
+struct StyleStack {
+    open: Vec<(&'static str, TextStyle)>,
+}
+
+impl StyleStack {
+    fn current(&self) -> TextStyle {
+        self.open.last().map_or_else(TextStyle::default, |(_, style)| *style)
+    }
+
+    fn push(&mut self, tag: &'static str, change: impl FnOnce(&mut TextStyle)) {
+        let mut style = self.current();
+        change(&mut style);
+        self.open.push((tag, style));
+    }
+
+    /// Closes the innermost `tag`, along with anything opened inside it
+    fn close(&mut self, tag: &str) -> Result<(), String> {
+        let Some(i) = self.open.iter().rposition(|(open, _)| *open == tag) else {
+            return Err(format!("'{{/{tag}}}' closes a tag that isn't open"));
+        };
+        self.open.truncate(i);
+        Ok(())
+    }
+}
+
+impl MarkedText {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut text = String::with_capacity(raw.len());
+        let mut markers = vec![];
+        let mut spans: Vec<StyledSpan> = vec![];
+        let mut styles = StyleStack::default();
+        let mut rest = raw;
+
+        // Ends the span running up to here, so the next one starts with whatever style's current after a tag
+        let mut end_span = |text: &str, style: TextStyle| {
+            let start = spans.last().map_or(0, |span| span.range.end);
+            if start == text.len() {
+                return;
+            }
+            match spans.last_mut() {
+                Some(last) if last.style == style => last.range.end = text.len(),
+                _ => spans.push(StyledSpan { range: start..text.len(), style }),
+            }
+        };
+
+        while let Some(open) = rest.find('{') {
+            text.push_str(&rest[..open]);
+            rest = &rest[open + 1..];
+            if let Some(after) = rest.strip_prefix('{') {
+                text.push('{');
+                rest = after;
+                continue;
+            }
+            let Some((tag, after)) = rest.split_once('}') else {
+                return Err(format!("Unclosed tag '{{{rest}' (write '{{{{' for a literal brace)"));
+            };
+            rest = after;
+
+            end_span(&text, styles.current());
+            let (name, value) = tag.trim().split_once(char::is_whitespace).unwrap_or((tag.trim(), ""));
+            let value = value.trim();
+            match name {
+                "pause" => markers.push((text.len(), TextMarker::Pause(parse_positive(name, value)?))),
+                "speed" if value.is_empty() => markers.push((text.len(), TextMarker::Speed(1.0))),
+                "speed" => markers.push((text.len(), TextMarker::Speed(parse_positive(name, value)?))),
+                "color" => {
+                    let color = parse_color(value)?;
+                    styles.push("color", |style| style.color = Some(color));
+                }
+                "i" => styles.push("i", |style| style.italic = true),
+                "size" => {
+                    let size = parse_positive(name, value)?;
+                    styles.push("size", |style| style.size *= size);
+                }
+                "shake" => styles.push("shake", |style| style.shake = true),
+                "wave" => styles.push("wave", |style| style.wave = true),
+                _ => match name.strip_prefix('/') {
+                    Some(closing @ ("color" | "i" | "size" | "shake" | "wave")) => styles.close(closing)?,
+                    _ => return Err(format!(
+                        "Unknown tag '{{{tag}}}'. Valid tags: pause | speed | color | i | size | shake | wave, and /closing ones for styles"
+                    )),
+                },
+            }
+        }
+        text.push_str(rest);
+        end_span(&text, styles.current());
+
+        Ok(MarkedText { text: text.into_boxed_str(), markers, spans })
+    }
+}
+
+fn parse_positive(name: &str, value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(n) if n > 0.0 && n.is_finite() => Ok(n),
+        _ => Err(format!("Bad value '{value}' for '{name}', expected a positive number")),
+    }
+}
+
+/// Parses `#rrggbb` or one of a handful of names, the same as macroquad's color constants in lowercase
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    if let Some(hex) = s.strip_prefix('#') {
+        return match u32::from_str_radix(hex, 16) {
+            Ok(rgb) if hex.len() == 6 => Ok(Color::from_hex(rgb)),
+            _ => Err(format!("Bad color '{s}', expected '#rrggbb'")),
+        };
+    }
+    const NAMED: &[(&str, Color)] = &[
+        ("black", BLACK), ("white", WHITE), ("gray", GRAY), ("darkgray", DARKGRAY), ("red", RED), ("maroon", MAROON),
+        ("orange", ORANGE), ("gold", GOLD), ("yellow", YELLOW), ("green", GREEN), ("darkgreen", DARKGREEN),
+        ("blue", BLUE), ("darkblue", DARKBLUE), ("skyblue", SKYBLUE), ("purple", PURPLE), ("violet", VIOLET),
+        ("pink", PINK), ("brown", BROWN), ("beige", BEIGE),
+    ];
+    NAMED.iter().find(|(name, _)| *name == s).map(|(_, color)| *color).ok_or_else(|| format!(
+        "Unknown color '{s}'. Valid colors: #rrggbb | {}",
+        NAMED.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(" | ")
+    ))
+}
+
+/// Splits `range` up by style, e.g. for drawing a row of text. Text without any spans is all unstyled
+pub fn styled_pieces(spans: &[StyledSpan], range: Range<usize>) -> impl Iterator<Item = (Range<usize>, TextStyle)> + '_ {
+    let unstyled = spans.is_empty().then(|| (range.clone(), TextStyle::default()));
+    spans.iter()
+        .filter_map(move |span| {
+            let piece = span.range.start.max(range.start)..span.range.end.min(range.end);
+            (piece.start < piece.end).then_some((piece, span.style))
+        })
+        .chain(unstyled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timing_markers() {
+        let marked = MarkedText::parse("Well...{pause 0.5} I {speed 0.25}suppose{speed}.").unwrap();
+        assert_eq!(&*marked.text, "Well... I suppose.");
+        assert_eq!(marked.markers, [(7, TextMarker::Pause(0.5)), (10, TextMarker::Speed(0.25)), (17, TextMarker::Speed(1.0))]);
+    }
+
+    #[test]
+    fn timing_errors() {
+        assert!(MarkedText::parse("{pause}").unwrap_err().contains("Bad value '' for 'pause'"));
+        assert!(MarkedText::parse("{pause -1}").is_err());
+        assert!(MarkedText::parse("{speed 0}").is_err());
+        assert!(MarkedText::parse("{speed inf}").is_err());
+    }
+
+    #[test]
+    fn literal_braces() {
+        let marked = MarkedText::parse("Braces are written {{like this}.").unwrap();
+        assert_eq!(&*marked.text, "Braces are written {like this}.");
+        assert!(marked.markers.is_empty());
+    }
+
+    fn styled(raw: &str) -> Vec<(&str, TextStyle)> {
+        let marked = MarkedText::parse(raw).unwrap();
+        let text: &'static str = Box::leak(marked.text);
+        marked.spans.into_iter().map(|span| (&text[span.range], span.style)).collect()
+    }
+
+    #[test]
+    fn styles_nest_and_close() {
+        let plain = TextStyle::default();
+        let red = TextStyle { color: Some(RED), ..plain };
+        let red_italic = TextStyle { italic: true, ..red };
+        assert_eq!(styled("That's {color red}{i}not{/i}{/color} it"), [
+            ("That's ", plain),
+            ("not", red_italic),
+            (" it", plain),
+        ]);
+        // Closing an outer tag closes everything opened inside it too
+        assert_eq!(styled("{color red}a{i}b{/color}c"), [("a", red), ("b", red_italic), ("c", plain)]);
+        // Sizes multiply, and what's left open runs to the end of the line
+        assert_eq!(styled("{size 2}{shake}a{size 1.5}{wave}b"), [
+            ("a", TextStyle { size: 2.0, shake: true, ..plain }),
+            ("b", TextStyle { size: 3.0, shake: true, wave: true, ..plain }),
+        ]);
+    }
+
+    #[test]
+    fn spans_cover_the_text() {
+        assert_eq!(styled("no markup"), [("no markup", TextStyle::default())]);
+        // Adjacent spans with the same style are merged, and empty ones dropped
+        assert_eq!(styled("a{i}{/i}b{color #ff0000}{/color}c"), [("abc", TextStyle::default())]);
+        assert!(MarkedText::parse("").unwrap().spans.is_empty());
+    }
+
+    #[test]
+    fn style_errors() {
+        assert!(MarkedText::parse("{/i}").unwrap_err().contains("closes a tag that isn't open"));
+        assert!(MarkedText::parse("{i}a{/color}").is_err());
+        assert!(MarkedText::parse("{bold}").unwrap_err().starts_with("Unknown tag '{bold}'"));
+        assert!(MarkedText::parse("{/bold}").unwrap_err().starts_with("Unknown tag '{/bold}'"));
+        assert!(MarkedText::parse("{size -2}").is_err());
+        assert!(MarkedText::parse("a {brace").unwrap_err().starts_with("Unclosed tag '{brace'"));
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("red"), Ok(RED));
+        assert_eq!(parse_color("#ff8000"), Ok(Color::from_hex(0xff8000)));
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("#gg0000").is_err());
+        assert!(parse_color("Red").unwrap_err().contains("Valid colors: #rrggbb | black"));
+    }
+}
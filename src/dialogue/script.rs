@@ -0,0 +1,499 @@
+//! Dialogue scripts: lines of text with labels, jumps and multiple-choice prompts in between.
+//!
+//! Files ending in `.dlg` are read as scripts:
+//! ```text
+//! # Comments take up a whole line, blank lines are ignored too
+//! # A label marks the node after it
+//! :start
+//! Hello there!
+//! Need anything?
+//! # Consecutive choices make up one prompt (blank lines and comments don't split it), one without a target carries on below it
+//! * Directions -> directions
+//! * Nothing
+//! Safe travels then.
+//! # Jumps to a label, `end` finishes the dialogue
+//! -> end
+//! # Conversations after the first start here if it's there, otherwise at the top again
+//! :repeat
+//! Back again?
+//! :directions
+//! # Directives change who's speaking for every line below them (in file order, not play order)
+//! @speaker Old Man
+//! @portrait sprites/old_man.png
+//! @expression happy
+//! The village is down the road.
+//! # On its own, `@speaker` goes back to whoever's being talked to, dropping any portrait and expression
+//! @speaker
+//! # Unlike the rest, applies to the whole script wherever it is: whether the world pauses or carries on
+//! # without the player while talking (see WorldMode's FromStr impl), pausing by default
+//! @world restrict
+//! # Conditions in front of a line, jump or effect skip it unless they hold, see [`super::conditions`]
+//! [talks == 0] Haven't seen you before.
+//! [flag met_mayor] -> mayor_chat
+//! # Effects change the game when reached
+//! $set asked_directions true
+//! # Choices can have conditions too, being hidden unless they hold. A prompt with none left is skipped
+//! * [item coin >= 5] Buy a map -> buy_map
+//! # Text from the string tables by key, see [`crate::localization`]. Speaker names can be keys too
+//! %old_man.farewell
+//! * %old_man.ask_again -> directions
+//! @speaker %names.mayor
+//! \* A line starting with a special character (: - * # @ [ $ % \) needs a backslash
+//! ```
+//! Text of lines and choices can have inline markup, see [`super::markup`].
+//!
+//! Anything else is read as a plain linear script, one line of dialogue per line of the file, all said by whoever's being talked to.
+//! Its text is shown as is, so braces in it are just braces rather than markup.
+use crate::prelude::*;
+
+/// Label every script has, jumping to it finishes the dialogue
+pub const END_LABEL: &str = "end";
+/// Optional label conversations after the first start at, e.g. for a shorter greeting
+pub const REPEAT_LABEL: &str = "repeat";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DialogueScript {
+    pub nodes: Vec<DialogueNode>,
+    /// What the world does while this is being talked through, see [`crate::focus`]
+    pub world: WorldMode,
+    /// Where each label points, see [`DialogueScript::label`]
+    labels: HashMap<Box<str>, usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogueNode {
+    Line(DialogueLine),
+    /// Carries on at the given node, [`DialogueScript::nodes`]' length meaning the end
+    Jump(usize),
+    /// Waits for the player to pick one
+    Choice(Vec<DialogueChoice>),
+    /// Carries on at the next node if the condition holds, otherwise skips to `otherwise` (past the node it guards)
+    Guard { condition: DialogueCondition, otherwise: usize },
+    /// Run as soon as it's reached, see [`DialogueHost::run_effect`]
+    Effect(DialogueEffect),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DialogueLine {
+    /// Markup already stripped out. Until [`DialogueScript::localize`]d, a keyed line's text is just its key
+    pub text: Box<str>,
+    /// String table key the text comes from, see [`crate::localization`]
+    pub key: Option<Box<str>>,
+    /// From the markup, see [`MarkedText::markers`]
+    pub markers: Vec<(usize, TextMarker)>,
+    /// From the markup, see [`MarkedText::spans`]
+    pub spans: Vec<StyledSpan>,
+    /// Who says it, None for whoever's being talked to. Can be a `%key`, see [`Localization::resolve`]
+    pub speaker: Option<Box<str>>,
+    /// Texture path, None for the speaker's default (see [`super::Speaker`])
+    pub portrait: Option<Box<str>>,
+    /// Variant of the portrait, see [`portrait_path`]
+    pub expression: Option<Box<str>>,
+}
+
+/// Texture path of a portrait with an expression, e.g. `sprites/old_man.png` + `happy` = `sprites/old_man_happy.png`
+pub fn portrait_path(portrait: &str, expression: Option<&str>) -> String {
+    let Some(expression) = expression else {
+        return portrait.to_string();
+    };
+    match portrait.rsplit_once('.').filter(|(_, ext)| !ext.contains('/')) {
+        Some((stem, ext)) => format!("{stem}_{expression}.{ext}"),
+        None => format!("{portrait}_{expression}"),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DialogueChoice {
+    /// Until [`DialogueScript::localize`]d, a keyed choice's text is just its key
+    pub text: Box<str>,
+    /// String table key the text comes from, see [`crate::localization`]
+    pub key: Option<Box<str>>,
+    /// Node picking this carries on at, None for right after the prompt
+    pub target: Option<usize>,
+    /// Hidden unless this holds
+    pub condition: Option<DialogueCondition>,
+}
+
+pub struct DialogueParseError {
+    /// What was being parsed, usually the asset path
+    source: Box<str>,
+    line: usize,
+    msg: String,
+}
+
+impl DialogueParseError {
+    pub fn dyn_boxed<T: ToString>(source: &str, line: usize, msg: T) -> Box<dyn Error> {
+        Box::new(DialogueParseError { source: Box::from(source), line, msg: msg.to_string() })
+    }
+}
+
+// Using Display formatting for Debug's impl as well, same as the other parsers' errors
+impl Debug for DialogueParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for DialogueParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{} Line #{}] {}", self.source, self.line, self.msg)
+    }
+}
+
+impl Error for DialogueParseError {}
+
+impl DialogueScript {
+    /// Every line of `text` is a line of dialogue, in order, taken as is without any markup
+    pub fn linear(text: &str) -> Self {
+        let nodes = text.lines()
+            .map(|line| DialogueNode::Line(DialogueLine { text: Box::from(line), ..Default::default() }))
+            .collect();
+        DialogueScript { nodes, world: WorldMode::default(), labels: HashMap::new() }
+    }
+
+    /// Parses the `.dlg` format, see the [module docs](self)
+    pub fn parse(source: &str, text: &str) -> GResult<Self> {
+        let mut nodes = vec![];
+        let mut labels = HashMap::new();
+        // Targets are resolved once every label is known: (line number, node, choice if any, label)
+        let mut unresolved: Vec<(usize, usize, Option<usize>, Box<str>)> = vec![];
+        let mut in_choices = false;
+        // Applied to every line from here on, set by directives
+        let mut speaking = DialogueLine::default();
+        let mut world = WorldMode::default();
+
+        for (i, raw_line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = raw_line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let was_in_choices = std::mem::take(&mut in_choices);
+            let (condition, line) = split_condition(source, line_number, line)?;
+            // Guards the node this line makes, `otherwise` being filled in once it's made
+            let guard = match condition {
+                Some(_) if line.is_empty() => return Err(DialogueParseError::dyn_boxed(source, line_number,
+                    "Condition has nothing after it to guard"
+                )),
+                Some(_) if line.starts_with([':', '@', '*']) => return Err(DialogueParseError::dyn_boxed(source, line_number,
+                    "Only lines, jumps and effects can have conditions in front, choices have theirs after the '*'"
+                )),
+                Some(condition) => {
+                    nodes.push(DialogueNode::Guard { condition, otherwise: 0 });
+                    Some(nodes.len() - 1)
+                }
+                None => None,
+            };
+
+            if let Some(label) = line.strip_prefix(':') {
+                let label = label.trim();
+                if label.is_empty() || label == END_LABEL {
+                    return Err(DialogueParseError::dyn_boxed(source, line_number, format_args!("Bad label ':{label}'")));
+                }
+                if labels.insert(Box::from(label), nodes.len()).is_some() {
+                    return Err(DialogueParseError::dyn_boxed(source, line_number, format_args!("Label ':{label}' is defined twice")));
+                }
+            } else if let Some(directive) = line.strip_prefix('@') {
+                let (name, value) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+                let value = Some(value.trim()).filter(|v| !v.is_empty()).map(Box::from);
+                match name {
+                    "speaker" => speaking = DialogueLine { speaker: value, ..Default::default() },
+                    "portrait" => speaking.portrait = value,
+                    "expression" => speaking.expression = value,
+                    "world" => world = value.unwrap_or_default().parse()
+                        .map_err(|msg| DialogueParseError::dyn_boxed(source, line_number, msg))?,
+                    _ => return Err(DialogueParseError::dyn_boxed(source, line_number, format_args!(
+                        "Unknown directive '@{name}'. Valid directives: @speaker | @portrait | @expression | @world"
+                    ))),
+                }
+            } else if let Some(target) = line.strip_prefix("->") {
+                unresolved.push((line_number, nodes.len(), None, Box::from(target.trim())));
+                nodes.push(DialogueNode::Jump(0));
+            } else if let Some(effect) = line.strip_prefix('$') {
+                let effect = effect.parse().map_err(|msg| DialogueParseError::dyn_boxed(source, line_number, msg))?;
+                nodes.push(DialogueNode::Effect(effect));
+            } else if let Some(choice) = line.strip_prefix('*') {
+                let (condition, choice) = split_condition(source, line_number, choice.trim())?;
+                let (text, target) = match choice.rsplit_once("->") {
+                    Some((text, target)) => (text.trim(), Some(target.trim())),
+                    None => (choice.trim(), None),
+                };
+                if !was_in_choices {
+                    nodes.push(DialogueNode::Choice(vec![]));
+                }
+                let node = nodes.len() - 1;
+                let Some(DialogueNode::Choice(choices)) = nodes.last_mut() else {
+                    unreachable!("a choice node was just pushed or continued");
+                };
+                if let Some(target) = target {
+                    unresolved.push((line_number, node, Some(choices.len()), Box::from(target)));
+                }
+                let (text, key) = match text.strip_prefix('%') {
+                    Some(key) => (Box::from(parse_string_key(source, line_number, key)?), Some(Box::from(key))),
+                    // Choices are drawn plain, so only the text's kept
+                    None => (parse_markup(source, line_number, text)?.text, None),
+                };
+                choices.push(DialogueChoice { text, key, target: None, condition });
+                in_choices = true;
+            } else if let Some(key) = line.strip_prefix('%') {
+                let key = parse_string_key(source, line_number, key)?;
+                nodes.push(DialogueNode::Line(DialogueLine { text: Box::from(key), key: Some(Box::from(key)), ..speaking.clone() }));
+            } else {
+                let text = line.strip_prefix('\\').unwrap_or(line);
+                let MarkedText { text, markers, spans } = parse_markup(source, line_number, text)?;
+                nodes.push(DialogueNode::Line(DialogueLine { text, markers, spans, ..speaking.clone() }));
+            }
+
+            if let Some(guard) = guard {
+                let end = nodes.len();
+                if let DialogueNode::Guard { otherwise, .. } = &mut nodes[guard] {
+                    *otherwise = end;
+                }
+            }
+        }
+
+        let end = nodes.len();
+        for (line_number, node, choice, label) in unresolved {
+            let target = match &*label {
+                END_LABEL => end,
+                label => *labels.get(label).ok_or_else(|| {
+                    DialogueParseError::dyn_boxed(source, line_number, format_args!("Unknown label '{label}'"))
+                })?,
+            };
+            match (&mut nodes[node], choice) {
+                (DialogueNode::Jump(jump), None) => *jump = target,
+                (DialogueNode::Choice(choices), Some(choice)) => choices[choice].target = Some(target),
+                _ => unreachable!("unresolved targets only point at jumps and choices"),
+            }
+        }
+
+        Ok(DialogueScript { nodes, world, labels })
+    }
+
+    /// Node a label points to, `end` giving [`DialogueScript::nodes`]' length
+    pub fn label(&self, label: &str) -> Option<usize> {
+        match label {
+            END_LABEL => Some(self.nodes.len()),
+            label => self.labels.get(label).copied(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Every line, wherever it is in the script
+    pub fn lines(&self) -> impl Iterator<Item = &DialogueLine> {
+        self.nodes.iter().filter_map(|node| match node {
+            DialogueNode::Line(line) => Some(line),
+            _ => None,
+        })
+    }
+
+    /// Copy with the text of every keyed line and choice, and every `%key` speaker name, looked up in `loc`.
+    /// Nodes stay where they were, so indices into one work for the other.
+    pub fn localize(&self, loc: &Localization) -> DialogueScript {
+        let mut script = self.clone();
+        for node in script.nodes.iter_mut() {
+            match node {
+                DialogueNode::Line(line) => {
+                    if let Some(key) = &line.key {
+                        let MarkedText { text, markers, spans } = loc.marked(key);
+                        (line.text, line.markers, line.spans) = (text, markers, spans);
+                    }
+                    if let Some(speaker) = line.speaker.as_mut() {
+                        *speaker = Box::from(loc.resolve(speaker));
+                    }
+                }
+                DialogueNode::Choice(choices) => {
+                    for choice in choices.iter_mut() {
+                        if let Some(key) = &choice.key {
+                            choice.text = loc.marked(key).text;
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        script
+    }
+}
+
+/// String table keys are written straight into `.lang` files, so can't have whitespace, `=` or `#` in them
+fn parse_string_key<'a>(source: &str, line_number: usize, key: &'a str) -> GResult<&'a str> {
+    let key = key.trim();
+    if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '=' || c == '#') {
+        return Err(DialogueParseError::dyn_boxed(source, line_number, format_args!("Bad string key '%{key}'")));
+    }
+    Ok(key)
+}
+
+/// Splits a `[condition]` off the front of a line, if it has one
+fn split_condition<'a>(source: &str, line_number: usize, line: &'a str) -> GResult<(Option<DialogueCondition>, &'a str)> {
+    let Some(rest) = line.strip_prefix('[') else {
+        return Ok((None, line));
+    };
+    // A quoted value in the condition might have a ']' of its own
+    let Some((condition, rest)) = find_unquoted(rest, ']').map(|i| (&rest[..i], &rest[i + 1..])) else {
+        return Err(DialogueParseError::dyn_boxed(source, line_number, "Condition is missing its closing ']'"));
+    };
+    let condition = condition.parse().map_err(|msg| DialogueParseError::dyn_boxed(source, line_number, msg))?;
+    Ok((Some(condition), rest.trim_start()))
+}
+
+fn parse_markup(source: &str, line_number: usize, text: &str) -> GResult<MarkedText> {
+    MarkedText::parse(text).map_err(|msg| DialogueParseError::dyn_boxed(source, line_number, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> DialogueScript {
+        DialogueScript::parse("test.dlg", text).unwrap()
+    }
+
+    fn parse_err(text: &str) -> String {
+        DialogueScript::parse("test.dlg", text).unwrap_err().to_string()
+    }
+
+    fn line(node: &DialogueNode) -> &str {
+        match node {
+            DialogueNode::Line(line) => &line.text,
+            other => panic!("{other:?} isn't a line"),
+        }
+    }
+
+    #[test]
+    fn labels_and_jumps() {
+        let script = parse("Hi\n-> later\nSkipped\n:later\nLater\n-> end\n:unused\n");
+        assert_eq!(script.len(), 5);
+        assert_eq!(script.label("later"), Some(3));
+        assert_eq!(script.label(END_LABEL), Some(5));
+        // Nothing after it, so pointing at the end
+        assert_eq!(script.label("unused"), Some(5));
+        assert_eq!(script.label("nowhere"), None);
+        assert_eq!(script.nodes[1], DialogueNode::Jump(3));
+        assert_eq!(script.nodes[4], DialogueNode::Jump(5));
+        assert_eq!(line(&script.nodes[3]), "Later");
+    }
+
+    #[test]
+    fn label_errors() {
+        assert_eq!(parse_err("Hi\n-> nowhere"), "[test.dlg Line #2] Unknown label 'nowhere'");
+        assert!(parse_err(":a\n:a").contains("defined twice"));
+        assert!(parse_err(":end").contains("Bad label"));
+        assert!(parse_err(":").contains("Bad label"));
+        assert!(parse_err("* Pick -> gone").contains("Unknown label 'gone'"));
+    }
+
+    #[test]
+    fn consecutive_choices_make_one_prompt() {
+        let script = parse("Pick one\n* First -> a\n\n# comment between\n* Second\n* [talks > 0] Third -> end\n:a\nA\n");
+        let DialogueNode::Choice(choices) = &script.nodes[1] else {
+            panic!("{:?}", script.nodes);
+        };
+        assert_eq!(script.len(), 3);
+        assert_eq!(choices.iter().map(|c| (&*c.text, c.target)).collect::<Vec<_>>(), [("First", Some(2)), ("Second", None), ("Third", Some(3))]);
+        assert!(choices[2].condition.is_some());
+    }
+
+    #[test]
+    fn a_line_between_choices_splits_them() {
+        let script = parse("* One\nBetween\n* Two\n");
+        assert!(matches!(&script.nodes[..], [DialogueNode::Choice(a), DialogueNode::Line(_), DialogueNode::Choice(b)] if a.len() == 1 && b.len() == 1));
+    }
+
+    #[test]
+    fn guards_skip_past_what_they_guard() {
+        let script = parse("[flag met] Welcome back\n[!talks] -> intro\n$set met\n:intro\nHello\n");
+        assert!(matches!(script.nodes[0], DialogueNode::Guard { otherwise: 2, .. }));
+        assert_eq!(line(&script.nodes[1]), "Welcome back");
+        assert!(matches!(script.nodes[2], DialogueNode::Guard { otherwise: 4, .. }));
+        assert_eq!(script.nodes[3], DialogueNode::Jump(5));
+        assert!(matches!(script.nodes[4], DialogueNode::Effect(DialogueEffect::SetFlag { .. })));
+        assert_eq!(line(&script.nodes[5]), "Hello");
+
+        assert!(parse_err("[flag met]").contains("nothing after it to guard"));
+        assert!(parse_err("[flag met] * Choice").contains("choices have theirs after"));
+        assert!(parse_err("[flag met Hi").contains("missing its closing"));
+    }
+
+    #[test]
+    fn speaker_directives() {
+        let script = parse("@speaker Old Man\n@portrait sprites/old_man.png\n@expression happy\nHi\n@expression\nStill him\n@speaker\n\\* Not a choice\n");
+        let speakers = script.lines().map(|l| (&*l.text, l.speaker.as_deref(), l.portrait.as_deref(), l.expression.as_deref())).collect::<Vec<_>>();
+        assert_eq!(speakers, [
+            ("Hi", Some("Old Man"), Some("sprites/old_man.png"), Some("happy")),
+            ("Still him", Some("Old Man"), Some("sprites/old_man.png"), None),
+            ("* Not a choice", None, None, None),
+        ]);
+        assert!(parse_err("Hi\n@volume 3").starts_with("[test.dlg Line #2] Unknown directive '@volume'"));
+    }
+
+    #[test]
+    fn portrait_paths() {
+        assert_eq!(portrait_path("sprites/old_man.png", Some("happy")), "sprites/old_man_happy.png");
+        assert_eq!(portrait_path("sprites/old_man.png", None), "sprites/old_man.png");
+        assert_eq!(portrait_path("sprites.v2/old_man", Some("sad")), "sprites.v2/old_man_sad");
+    }
+
+    #[test]
+    fn linear_scripts_are_plain_text() {
+        let script = DialogueScript::linear("Hi {there\n* Not a choice {pause 1}\n");
+        assert_eq!(script.nodes.iter().map(line).collect::<Vec<_>>(), ["Hi {there", "* Not a choice {pause 1}"]);
+        assert!(script.lines().all(|l| l.markers.is_empty() && l.spans.is_empty()));
+        assert_eq!(script.label(END_LABEL), Some(2));
+    }
+
+    #[test]
+    fn markup_in_scripts() {
+        let script = parse("Wait{pause 1} {i}what{/i}?\n");
+        assert_eq!(line(&script.nodes[0]), "Wait what?");
+        assert!(parse_err("Hi\nA {brace").starts_with("[test.dlg Line #2]"));
+    }
+
+    #[test]
+    fn string_keys() {
+        let script = parse("%elder.greeting\n* %elder.ask -> end\n@speaker %names.elder\nHi\n");
+        let DialogueNode::Line(greeting) = &script.nodes[0] else { panic!() };
+        assert_eq!(greeting.key.as_deref(), Some("elder.greeting"));
+        let DialogueNode::Choice(choices) = &script.nodes[1] else { panic!() };
+        assert_eq!(choices[0].key.as_deref(), Some("elder.ask"));
+        assert!(parse_err("%bad key").contains("Bad string key '%bad key'"));
+        assert!(parse_err("* % -> end").contains("Bad string key"));
+    }
+
+    #[test]
+    fn localizing() {
+        let strings = |code, text| StringTable::from_kv(code, &KvFile::parse("test.lang", text).unwrap()).unwrap();
+        let loc = Localization::new(
+            strings("fr", "elder.greeting = Bien le {i}bonjour{/i}\nnames.elder = L'Ancien\n"),
+            Some(strings("en", "elder.ask = Ask\n")),
+        );
+        let script = parse("%elder.greeting\n* %elder.ask -> end\n* %elder.missing -> end\n@speaker %names.elder\nHi\n");
+        let localized = script.localize(&loc);
+        assert_eq!(localized.len(), script.len());
+
+        let DialogueNode::Line(greeting) = &localized.nodes[0] else { panic!() };
+        assert_eq!(&*greeting.text, "Bien le bonjour");
+        assert!(greeting.spans.iter().any(|span| span.style.italic));
+        let DialogueNode::Choice(choices) = &localized.nodes[1] else { panic!() };
+        assert_eq!(choices.iter().map(|c| &*c.text).collect::<Vec<_>>(), ["Ask", "elder.missing"]);
+        let DialogueNode::Line(hi) = &localized.nodes[2] else { panic!() };
+        assert_eq!((&*hi.text, hi.speaker.as_deref()), ("Hi", Some("L'Ancien")));
+    }
+
+    #[test]
+    fn conditions_with_quoted_values() {
+        let script = parse("$set rumor \"the mill, burned]\"\n[flag rumor == \"the mill, burned]\"] Heard?\n");
+        let DialogueNode::Guard { condition, .. } = &script.nodes[1] else { panic!("{:?}", script.nodes[1]) };
+        assert_eq!(condition.clauses.len(), 1);
+        assert_eq!(line(&script.nodes[2]), "Heard?");
+        assert!(parse_err("[flag rumor == \"unclosed] Hi").contains("missing its closing ']'"));
+    }
+}
@@ -1,12 +1,16 @@
-pub mod player;
-pub mod npc;
+pub mod components;
 pub mod entity_builder;
 pub mod entity_manager;
+pub mod prefab;
+pub mod movement;
+pub mod facing;
 
-pub use player::*;
-pub use npc::*;
+pub use components::*;
 pub use entity_builder::*;
 pub use entity_manager::*;
+pub use prefab::*;
+pub use movement::*;
+pub use facing::*;
 
 use crate::prelude::*;
 
@@ -17,55 +21,176 @@ pub struct Entity {
     pub texture: Texture2D,
     pub show_hitbox: bool,
     pub velocity: Vec2,
-    pub acceleration: Vec2,
-    pub id: usize,
+    pub movement: MovementParams,
+    pub platformer: PlatformerState,
+    pub facing: Facing,
+    /// What it was built from, None if built by hand
+    pub prefab: Option<PrefabSource>,
+    /// Assigned by the [`EntityManager`] on spawn, [`EntityId::UNSPAWNED`] before that
+    pub id: EntityId,
+
+    // Components, see [`components`]
+    pub controller: Option<InputControl>,
+    pub talker: Option<Talker>,
+    pub behavior: Option<Behavior>,
+    pub collider: Option<Collider>,
+    pub animation: Option<Animation>,
+    pub interactable: Option<Interactable>,
 }
 
 impl Entity {
+    /// Every constructor ends up here, so a new field only needs adding once
+    fn new(boundary: Rect, draw_size: Vec2, rotation: f32, texture: Texture2D) -> Self {
+        Entity {
+            boundary,
+            draw_size,
+            rotation,
+            texture,
+            show_hitbox: false,
+            velocity: vec2(0.0, 0.0),
+            movement: MovementParams::default(),
+            platformer: PlatformerState::default(),
+            facing: Facing::default(),
+            prefab: None,
+            id: EntityId::UNSPAWNED,
+            controller: None,
+            talker: None,
+            behavior: None,
+            collider: None,
+            animation: None,
+            interactable: None,
+        }
+    }
+
     async fn build_from_center(
+        assets: &AssetResolver,
         (x, y): (f32, f32),
         (bwidth, bheight): (f32, f32),
         draw_size: Vec2,
         rotation: f32,
         texture_path: &str,
-        id: usize,
     ) -> GResult<Self> {
-        let texture = load_texture(texture_path).await?;
-    
         let tl_x = x - bwidth / 2.0;
         let tl_y = y - bheight / 2.0;
 
-        Ok(Entity {
-            boundary: Rect::new(tl_x, tl_y, bwidth, bheight),
-            draw_size,
-            rotation,
-            texture,
-            show_hitbox: false,
-            velocity: vec2(0.0, 0.0),
-            acceleration: vec2(0.0, 0.0),
-            id
-        }) 
+        Entity::build_from_boundary(assets, Rect::new(tl_x, tl_y, bwidth, bheight), draw_size, rotation, texture_path).await
     }
 
     async fn build_from_boundary(
+        assets: &AssetResolver,
         boundary: Rect,
         draw_size: Vec2,
         rotation: f32,
         texture_path: &str,
-        id: usize
     ) -> GResult<Self> {
-        let texture = load_texture(texture_path).await?;
-    
-        Ok(Entity {
-            boundary,
-            draw_size,
-            rotation,
-            texture,
-            show_hitbox: false,
-            velocity: vec2(0.0, 0.0),
-            acceleration: vec2(0.0, 0.0),
-            id
-        }) 
+        let texture = assets.load_texture(texture_path).await?;
+        Ok(Entity::new(boundary, draw_size, rotation, texture))
+    }
+}
+
+#[cfg(test)]
+impl Entity {
+    /// Entity with a placeholder texture, as loading a real one needs a window
+    pub(crate) fn bare(boundary: Rect) -> Self {
+        use macroquad::miniquad::{ RawId, TextureId };
+        let texture = Texture2D::from_miniquad_texture(TextureId::from_raw_id(RawId::OpenGl(0)));
+        Entity::new(boundary, boundary.size(), 0.0, texture)
+    }
+}
+
+// Component builders
+impl Entity {
+    pub fn with_movement(mut self, movement: MovementParams) -> Self {
+        self.movement = movement;
+        self
+    }
+
+    pub fn with_facing(mut self, facing: Facing) -> Self {
+        self.facing = facing;
+        self
+    }
+
+    pub fn with_controller(mut self, controller: InputControl) -> Self {
+        self.controller = Some(controller);
+        self
+    }
+
+    pub fn with_talker(mut self, talker: Talker) -> Self {
+        self.talker = Some(talker);
+        self
+    }
+
+    pub fn with_behavior(mut self, behavior: Behavior) -> Self {
+        self.behavior = Some(behavior);
+        self
+    }
+
+    pub fn with_collider(mut self, collider: Collider) -> Self {
+        self.collider = Some(collider);
+        self
+    }
+
+    pub fn with_animation(mut self, animation: Animation) -> Self {
+        self.animation = Some(animation);
+        self
+    }
+
+    pub fn with_interactable(mut self, interactable: Interactable) -> Self {
+        self.interactable = Some(interactable);
+        self
+    }
+
+    /// Whether the entity moves on its own accord each frame, via input or AI
+    pub fn is_mover(&self) -> bool {
+        self.controller.is_some() || self.behavior.is_some()
+    }
+
+    /// Direction the entity wants to move in this frame, input taking priority over AI
+    pub fn move_intent(&self) -> Vec2 {
+        if let Some(controller) = self.controller.as_ref() {
+            controller.axes()
+        } else if let Some(behavior) = self.behavior.as_ref() {
+            behavior.steering()
+        } else {
+            Vec2::ZERO
+        }
+    }
+
+    /// Turns toward whatever the entity's AI is looking at, otherwise the way it wants to move
+    pub fn update_facing(&mut self) {
+        let looking = self.behavior.as_ref().map_or(Vec2::ZERO, Behavior::looking);
+        let toward = if looking != Vec2::ZERO { looking } else { self.move_intent() };
+        self.facing.turn_toward(toward);
+    }
+
+    /// Has the entity walk to `destination` and stay there from now on (see [`Behavior::go_to`]), still facing the player
+    /// when talking unless its old behavior didn't
+    pub fn go_to(&mut self, destination: Vec2) {
+        let face_player_when_talking = self.behavior.as_ref().is_none_or(|behavior| behavior.face_player_when_talking);
+        self.behavior = Some(Behavior::go_to(destination).with_face_player_when_talking(face_player_when_talking));
+    }
+}
+
+impl Entity {
+    /// [`Updateable::update_velocity`] for [`PhysicsMode::Platformer`] maps
+    pub fn update_platformer_velocity(&mut self, dt: f32) {
+        let jump_input = self.controller.as_ref().map_or((false, false), InputControl::jump_input);
+        let intent = self.move_intent();
+        self.velocity = self.movement.step_platformer_velocity(&mut self.platformer, self.velocity, intent, jump_input, dt);
+    }
+}
+
+impl Updateable for Entity {
+    fn update_velocity(&mut self, dt: f32) {
+        self.velocity = self.movement.step_velocity(self.velocity, self.move_intent(), dt);
+    }
+
+    fn update_x(&mut self, dt: f32) {
+        self.offset(vec2(self.velocity.x * dt, 0.0));
+    }
+
+    fn update_y(&mut self, dt: f32) {
+        self.offset(vec2(0.0, self.velocity.y * dt));
     }
 }
 
@@ -82,7 +207,7 @@ pub trait IsEntity {
     fn ref_entity(&self) -> &Entity;
     fn mut_entity(&mut self) -> &mut Entity;
 
-    fn id(&self) -> usize {
+    fn id(&self) -> EntityId {
         self.ref_entity().id
     }
 }
@@ -96,20 +221,29 @@ impl<T: IsEntity> RectBounded for T {
     }
 }
 
+/// Entities without a collider still have a shape, their boundary
+impl<T: IsEntity> ShapeBounded for T {
+    fn shape(&self) -> Shape {
+        let this = self.ref_entity();
+        this.collider.map(|c| c.shape).unwrap_or_default().around(this.boundary, this.rotation)
+    }
+}
+
 impl<T: IsEntity> Drawable for T {
     fn draw(&self) {
         let this = self.ref_entity();
-        let params = DrawTextureParams {
+        let mut params = DrawTextureParams {
             dest_size: Some(this.draw_size),
+            source: this.animation.as_ref().map(Animation::source_rect),
             rotation: this.rotation,
             ..Default::default()
         };
+        this.facing.apply_to(&mut params, this.texture.size());
         let tl = this.boundary.center() - this.draw_size / 2.0; 
         draw_texture_ex(&this.texture, tl.x, tl.y, WHITE, params);
 
         if this.show_hitbox {
-            let Rect { x, y, w, h } = this.boundary;
-            draw_rectangle_lines(x, y, w, h, 4.0, BLACK);
+            self.shape().draw_lines(4.0, BLACK);
         }
     }
 }
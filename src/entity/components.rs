@@ -0,0 +1,15 @@
+//! Optional pieces of data/behavior an [`Entity`] can carry.
+//! An entity is whatever its components make it, e.g. the player is just an entity with an [`InputControl`].
+pub mod input_control;
+pub mod talker;
+pub mod behavior;
+pub mod collider;
+pub mod sprite_animation;
+pub mod interactable;
+
+pub use input_control::*;
+pub use talker::*;
+pub use behavior::*;
+pub use collider::*;
+pub use sprite_animation::*;
+pub use interactable::*;
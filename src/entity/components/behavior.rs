@@ -0,0 +1,250 @@
+use crate::prelude::*;
+use macroquad::rand::gen_range;
+
+/// How close (in logical pixels) counts as having arrived at a point
+const ARRIVE_DISTANCE: f32 = 4.0;
+
+/// AI driving an entity's movement.
+/// Only decides where to go each frame via [`Behavior::think`], the actual movement (and collision) is the same as the player's.
+#[derive(Clone, Debug)]
+pub struct Behavior {
+    pub kind: BehaviorKind,
+    /// Stop and look at the player while they're in dialogue with us
+    pub face_player_when_talking: bool,
+    /// Normalized (or zero) direction we want to move this frame
+    steering: Vec2,
+    /// Normalized (or zero) direction we're looking in if we stopped to look at something
+    looking: Vec2,
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum BehaviorKind {
+    /// Stands still
+    #[default]
+    Idle,
+    /// Walks between waypoints (absolute positions) in order, looping back to the first
+    Patrol {
+        waypoints: Vec<Vec2>,
+        next: usize,
+    },
+    /// Walks to `destination` and stays there, e.g. when sent somewhere by dialogue (see [`DialogueEffect::Move`])
+    GoTo {
+        destination: Vec2,
+    },
+    /// Walks to random points within `radius` of where it started, waiting a bit at each
+    Wander {
+        radius: f32,
+        /// Set to the entity's position on the first think if None
+        origin: Option<Vec2>,
+        target: Option<Vec2>,
+        /// Seconds left until a new target is picked, also how long we give up on a target we can't reach
+        timer: f32,
+    },
+    /// Walks toward the player, stopping `min_distance` away
+    Follow {
+        min_distance: f32,
+    },
+    /// Walks away from the player while they're within `radius`
+    Flee {
+        radius: f32,
+    },
+}
+
+/// What a [`Behavior`] gets to know about the world when thinking
+pub struct BehaviorContext {
+    pub player_position: Vec2,
+    /// Whether the thinking entity is the one the player is currently in dialogue with
+    pub in_dialogue: bool,
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Behavior::new(BehaviorKind::Idle)
+    }
+}
+
+impl Behavior {
+    pub fn new(kind: BehaviorKind) -> Self {
+        Behavior { kind, face_player_when_talking: true, steering: Vec2::ZERO, looking: Vec2::ZERO }
+    }
+
+    pub fn patrol(waypoints: Vec<Vec2>) -> Self {
+        Behavior::new(BehaviorKind::Patrol { waypoints, next: 0 })
+    }
+
+    pub fn go_to(destination: Vec2) -> Self {
+        Behavior::new(BehaviorKind::GoTo { destination })
+    }
+
+    pub fn wander(radius: f32) -> Self {
+        Behavior::new(BehaviorKind::Wander { radius, origin: None, target: None, timer: 0.0 })
+    }
+
+    pub fn follow(min_distance: f32) -> Self {
+        Behavior::new(BehaviorKind::Follow { min_distance })
+    }
+
+    pub fn flee(radius: f32) -> Self {
+        Behavior::new(BehaviorKind::Flee { radius })
+    }
+
+    pub fn with_face_player_when_talking(mut self, face_player_when_talking: bool) -> Self {
+        self.face_player_when_talking = face_player_when_talking;
+        self
+    }
+
+    pub fn steering(&self) -> Vec2 {
+        self.steering
+    }
+
+    pub fn looking(&self) -> Vec2 {
+        self.looking
+    }
+
+    /// Decides this frame's [`Behavior::steering`] given the entity's current position
+    pub fn think(&mut self, position: Vec2, ctx: &BehaviorContext, dt: f32) {
+        self.looking = Vec2::ZERO;
+
+        if ctx.in_dialogue && self.face_player_when_talking {
+            self.steering = Vec2::ZERO;
+            self.looking = (ctx.player_position - position).normalize_or_zero();
+            return;
+        }
+
+        self.steering = match &mut self.kind {
+            BehaviorKind::Idle => Vec2::ZERO,
+            BehaviorKind::Patrol { waypoints, next } => {
+                if waypoints.is_empty() {
+                    Vec2::ZERO
+                } else {
+                    *next %= waypoints.len();
+                    if position.distance(waypoints[*next]) <= ARRIVE_DISTANCE {
+                        *next = (*next + 1) % waypoints.len();
+                    }
+                    toward(position, waypoints[*next])
+                }
+            }
+            BehaviorKind::GoTo { destination } => {
+                if position.distance(*destination) <= ARRIVE_DISTANCE {
+                    Vec2::ZERO
+                } else {
+                    toward(position, *destination)
+                }
+            }
+            BehaviorKind::Wander { radius, origin, target, timer } => {
+                let origin = *origin.get_or_insert(position);
+                *timer -= dt;
+                match *target {
+                    Some(t) if position.distance(t) > ARRIVE_DISTANCE && *timer > 0.0 => toward(position, t),
+                    Some(_) => {
+                        // Arrived (or gave up), so take a breather before the next target
+                        *target = None;
+                        *timer = gen_range(1.0, 3.0);
+                        Vec2::ZERO
+                    }
+                    None if *timer > 0.0 => Vec2::ZERO,
+                    None => {
+                        let angle = gen_range(0.0, std::f32::consts::TAU);
+                        let distance = gen_range(0.0, *radius);
+                        *target = Some(origin + Vec2::from_angle(angle) * distance);
+                        *timer = 5.0;
+                        Vec2::ZERO
+                    }
+                }
+            }
+            BehaviorKind::Follow { min_distance } => {
+                if position.distance(ctx.player_position) > *min_distance {
+                    toward(position, ctx.player_position)
+                } else {
+                    Vec2::ZERO
+                }
+            }
+            BehaviorKind::Flee { radius } => {
+                if position.distance(ctx.player_position) < *radius {
+                    toward(ctx.player_position, position)
+                } else {
+                    Vec2::ZERO
+                }
+            }
+        };
+    }
+}
+
+fn toward(from: Vec2, to: Vec2) -> Vec2 {
+    (to - from).normalize_or_zero()
+}
+
+/// Parses the prefab file form of a behavior, e.g. `wander 150` or `patrol 0,0 100,0 100,100`
+impl std::str::FromStr for Behavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let kind = parts.next().unwrap_or_default();
+        let args = parts.collect::<Vec<_>>();
+
+        let single_f32 = || match args.as_slice() {
+            [n] => n.parse::<f32>().map_err(|e| format!("'{kind}' expects a single number: {e}")),
+            _ => Err(format!("'{kind}' expects a single number")),
+        };
+
+        Ok(match kind {
+            "idle" => Behavior::new(BehaviorKind::Idle),
+            "wander" => Behavior::wander(single_f32()?),
+            "follow" => Behavior::follow(single_f32()?),
+            "flee" => Behavior::flee(single_f32()?),
+            "patrol" => {
+                let waypoints = args.iter()
+                    .map(|point| match crate::kv_file::parse_floats(point).as_deref() {
+                        Some(&[x, y]) => Ok(vec2(x, y)),
+                        _ => Err(format!("Bad patrol waypoint '{point}', expected 'x,y'")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Behavior::patrol(waypoints)
+            }
+            _ => return Err(format!("Unknown behavior '{kind}'. Valid behaviors: idle | wander <radius> | follow <min distance> | flee <radius> | patrol <x,y> <x,y>...")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTX: BehaviorContext = BehaviorContext { player_position: Vec2::ZERO, in_dialogue: false };
+
+    #[test]
+    fn going_to_a_point_stops_there() {
+        let mut behavior = Behavior::go_to(vec2(100.0, 50.0));
+        let mut position = vec2(0.0, 0.0);
+        for _ in 0..200 {
+            behavior.think(position, &CTX, 1.0 / 60.0);
+            position += behavior.steering() * 3.0;
+        }
+        assert!(position.distance(vec2(100.0, 50.0)) <= ARRIVE_DISTANCE, "{position}");
+        // Stays put once there rather than hovering about the point
+        behavior.think(position, &CTX, 1.0 / 60.0);
+        assert_eq!(behavior.steering(), Vec2::ZERO);
+    }
+
+    #[test]
+    fn patrolling_loops_through_waypoints() {
+        let mut behavior = Behavior::patrol(vec![vec2(0.0, 0.0), vec2(10.0, 0.0)]);
+        behavior.think(vec2(0.0, 0.0), &CTX, 0.0);
+        assert_eq!(behavior.steering(), vec2(1.0, 0.0));
+        behavior.think(vec2(10.0, 0.0), &CTX, 0.0);
+        assert_eq!(behavior.steering(), vec2(-1.0, 0.0));
+    }
+
+    #[test]
+    fn faces_the_player_when_talking() {
+        let ctx = BehaviorContext { player_position: vec2(0.0, 10.0), in_dialogue: true };
+        let mut behavior = Behavior::go_to(vec2(100.0, 0.0));
+        behavior.think(Vec2::ZERO, &ctx, 0.0);
+        assert_eq!((behavior.steering(), behavior.looking()), (Vec2::ZERO, vec2(0.0, 1.0)));
+
+        let mut behavior = behavior.with_face_player_when_talking(false);
+        behavior.think(Vec2::ZERO, &ctx, 0.0);
+        assert_eq!(behavior.steering(), vec2(1.0, 0.0));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::prelude::*;
+use std::str::FromStr;
+
+/// Makes an entity (or map geometry) take part in collision.
+///
+/// A mover is blocked by something when its `mask` has any of that thing's `layers`, and neither is a trigger.
+/// Triggers never block or get blocked, they only report overlaps with movers whose layers are in their mask.
+///
+/// Between two entities blocking each other, the overlap is split by `mass` (see [`crate::collision::CollisionWorld`]),
+/// so the lighter one gets shoved further. Immovable ones never get shoved, same as map geometry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collider {
+    /// Layers this is on
+    pub layers: CollisionLayers,
+    /// Layers this collides with
+    pub mask: CollisionLayers,
+    pub trigger: bool,
+    /// What shape the owner's boundary stands in for
+    pub shape: ColliderShape,
+    pub mass: f32,
+    pub immovable: bool,
+}
+
+impl Default for Collider {
+    fn default() -> Self {
+        Collider {
+            layers: CollisionLayers::WORLD,
+            mask: CollisionLayers::ALL,
+            trigger: false,
+            shape: ColliderShape::Rect,
+            mass: 1.0,
+            immovable: false,
+        }
+    }
+}
+
+impl Collider {
+    pub fn new(layers: CollisionLayers, mask: CollisionLayers) -> Self {
+        Collider { layers, mask, ..Default::default() }
+    }
+
+    pub fn trigger(layers: CollisionLayers, mask: CollisionLayers) -> Self {
+        Collider { layers, mask, trigger: true, ..Default::default() }
+    }
+
+    pub fn with_shape(mut self, shape: ColliderShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    pub fn immovable(mut self) -> Self {
+        self.immovable = true;
+        self
+    }
+
+    /// Whether a mover with this collider gets stopped by `other`
+    pub fn is_blocked_by(&self, other: &Collider) -> bool {
+        !self.trigger && !other.trigger && self.mask.intersects(other.layers)
+    }
+
+    /// Whether this (trigger) collider reports overlapping `other`
+    pub fn triggers_on(&self, other: &Collider) -> bool {
+        self.trigger && self.mask.intersects(other.layers)
+    }
+}
+
+/// Collision shape fitted to an entity's hitbox (its boundary) and rotation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColliderShape {
+    /// The boundary itself, ignores rotation
+    #[default]
+    Rect,
+    /// Largest circle fitting in the boundary
+    Circle,
+    /// The boundary, rotated about its center
+    OrientedRect,
+    /// Pill running along the boundary's longer side, rotated about its center
+    Capsule,
+}
+
+impl ColliderShape {
+    pub fn around(self, boundary: Rect, rotation: f32) -> Shape {
+        let center = boundary.center();
+        let half_extents = boundary.size() / 2.0;
+        match self {
+            ColliderShape::Rect => Shape::Rect(boundary),
+            ColliderShape::Circle => Shape::Circle { center, radius: half_extents.min_element() },
+            ColliderShape::OrientedRect => Shape::Obb { center, half_extents, rotation },
+            ColliderShape::Capsule => {
+                let radius = half_extents.min_element();
+                let spine = if half_extents.x > half_extents.y {
+                    vec2(half_extents.x - radius, 0.0)
+                } else {
+                    vec2(0.0, half_extents.y - radius)
+                };
+                let spine = Vec2::from_angle(rotation).rotate(spine);
+                Shape::Capsule { a: center - spine, b: center + spine, radius }
+            }
+        }
+    }
+}
+
+/// Parses `rect`, `circle`, `oriented_rect` or `capsule`
+impl FromStr for ColliderShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "rect" => ColliderShape::Rect,
+            "circle" => ColliderShape::Circle,
+            "oriented_rect" => ColliderShape::OrientedRect,
+            "capsule" => ColliderShape::Capsule,
+            _ => return Err(format!("Unknown collider shape '{s}'. Valid shapes: rect | circle | oriented_rect | capsule")),
+        })
+    }
+}
+
+/// Bitset of collision layers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CollisionLayers(pub u32);
+
+impl CollisionLayers {
+    pub const NONE: CollisionLayers = CollisionLayers(0);
+    pub const ALL: CollisionLayers = CollisionLayers(u32::MAX);
+    /// Map geometry and anything else static
+    pub const WORLD: CollisionLayers = CollisionLayers(1 << 0);
+    pub const PLAYER: CollisionLayers = CollisionLayers(1 << 1);
+    pub const NPC: CollisionLayers = CollisionLayers(1 << 2);
+    pub const PICKUP: CollisionLayers = CollisionLayers(1 << 3);
+
+    /// Names usable in data files, see the [`FromStr`] impl
+    pub const NAMED: &[(&str, CollisionLayers)] = &[
+        ("none", CollisionLayers::NONE),
+        ("all", CollisionLayers::ALL),
+        ("world", CollisionLayers::WORLD),
+        ("player", CollisionLayers::PLAYER),
+        ("npc", CollisionLayers::NPC),
+        ("pickup", CollisionLayers::PICKUP),
+    ];
+
+    pub fn intersects(self, other: CollisionLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl std::ops::BitOr for CollisionLayers {
+    type Output = CollisionLayers;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        CollisionLayers(self.0 | rhs.0)
+    }
+}
+
+/// Parses `|` separated layer names, e.g. `world|player`
+impl FromStr for CollisionLayers {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('|').map(str::trim).try_fold(CollisionLayers::NONE, |layers, name| {
+            CollisionLayers::NAMED.iter()
+                .find(|(n, _)| *n == name)
+                .map(|&(_, layer)| layers | layer)
+                .ok_or_else(|| format!(
+                    "Unknown collision layer '{name}'. Valid layers: {:?}",
+                    CollisionLayers::NAMED.iter().map(|(n, _)| n).collect::<Vec<_>>()
+                ))
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use crate::prelude::*;
+
+/// Lets the keyboard drive an entity's movement
+#[derive(Clone, Copy, Debug)]
+pub struct InputControl {
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
+    /// Only used in platformer mode
+    pub jump: KeyCode,
+    /// Held directions as of the last [`InputControl::poll`], each axis -1, 0 or 1
+    axes: Vec2,
+    /// Jump (pressed this frame, held) as of the last [`InputControl::poll`]
+    jump_input: (bool, bool),
+}
+
+impl Default for InputControl {
+    fn default() -> Self {
+        InputControl {
+            left: KeyCode::A,
+            right: KeyCode::D,
+            up: KeyCode::W,
+            down: KeyCode::S,
+            jump: KeyCode::Space,
+            axes: Vec2::ZERO,
+            jump_input: (false, false),
+        }
+    }
+}
+
+impl InputControl {
+    /// Reads the keyboard for this frame
+    pub fn poll(&mut self) {
+        self.axes = vec2(Self::axis(self.left, self.right), Self::axis(self.up, self.down));
+        self.jump_input = (is_key_pressed(self.jump), is_key_down(self.jump));
+    }
+
+    /// Lets go of everything, for while something else has input (see [`crate::focus`])
+    pub fn release(&mut self) {
+        self.axes = Vec2::ZERO;
+        self.jump_input = (false, false);
+    }
+
+    pub fn jump_input(&self) -> (bool, bool) {
+        self.jump_input
+    }
+
+    pub fn axes(&self) -> Vec2 {
+        self.axes
+    }
+
+    fn axis(negative: KeyCode, positive: KeyCode) -> f32 {
+        let mut axis = 0.0;
+        if is_key_down(negative) {
+            axis -= 1.0;
+        }
+        if is_key_down(positive) {
+            axis += 1.0;
+        }
+        axis
+    }
+}
@@ -0,0 +1,24 @@
+/// Something the player can interact with by facing it and pressing [`crate::constants::INTERACT_KEY`]
+#[derive(Clone, Debug)]
+pub struct Interactable {
+    /// How far (in logical pixels) the gap between the player's and the entity's boundaries can be
+    pub radius: f32,
+    pub action: InteractAction,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InteractAction {
+    /// Starts the entity's dialogue, see [`super::Talker`]
+    Talk,
+}
+
+impl std::str::FromStr for InteractAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "talk" => Ok(InteractAction::Talk),
+            _ => Err(format!("Unknown interact action '{s}'. Valid actions: talk")),
+        }
+    }
+}
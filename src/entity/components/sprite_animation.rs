@@ -0,0 +1,34 @@
+use crate::prelude::*;
+
+/// Plays an entity's texture as a horizontal strip of equally sized frames
+#[derive(Clone, Debug)]
+pub struct Animation {
+    /// Size of a single frame in texture pixels
+    pub frame_size: Vec2,
+    pub frame_count: usize,
+    pub fps: f32,
+    elapsed: f32,
+}
+
+impl Animation {
+    pub fn new(frame_size: Vec2, frame_count: usize, fps: f32) -> Self {
+        Animation { frame_size, frame_count: frame_count.max(1), fps, elapsed: 0.0 }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+
+    pub fn restart(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    pub fn frame(&self) -> usize {
+        (self.elapsed * self.fps) as usize % self.frame_count
+    }
+
+    /// Source rect of the current frame, for [`DrawTextureParams::source`]
+    pub fn source_rect(&self) -> Rect {
+        Rect::new(self.frame() as f32 * self.frame_size.x, 0.0, self.frame_size.x, self.frame_size.y)
+    }
+}
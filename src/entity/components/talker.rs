@@ -0,0 +1,56 @@
+use crate::prelude::*;
+
+/// Something that can be talked to
+pub struct Talker {
+    pub dialogue: Dialogue,
+    /// Says every line the dialogue doesn't give another speaker
+    pub speaker: Speaker,
+    /// Every portrait the dialogue (or speaker) can show, by texture path, loaded up front so drawing never waits on them
+    pub portraits: Arc<HashMap<Box<str>, Texture2D>>,
+    /// Conversations started with it so far, for `talks` conditions in dialogue. Resuming one doesn't count
+    pub talks: u32,
+    /// Node an interrupted conversation left off at, picked up from next time, see [`DialogueEvent::Interrupted`]
+    pub resume: Option<usize>,
+}
+
+/// Name (and default portrait) shown on the dialogue box
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Speaker {
+    pub name: Box<str>,
+    /// Texture path, see [`portrait_path`] for expressions
+    pub portrait: Option<Box<str>>,
+}
+
+impl Speaker {
+    /// Name on the name plate for a line
+    pub fn name_for<'a>(&'a self, line: &'a DialogueLine) -> &'a str {
+        line.speaker.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Portrait texture path for a line, if it shows one.
+    /// Lines said by someone else only show a portrait if they give one themselves.
+    pub fn portrait_for(&self, line: &DialogueLine) -> Option<String> {
+        let portrait = match (&line.portrait, &line.speaker) {
+            (Some(portrait), _) => portrait,
+            (None, None) => self.portrait.as_ref()?,
+            (None, Some(_)) => return None,
+        };
+        Some(portrait_path(portrait, line.expression.as_deref()))
+    }
+}
+
+impl Talker {
+    pub async fn load(assets: &AssetResolver, dialogue_path: &str, speaker: Speaker) -> GResult<Self> {
+        let dialogue = build_dialogue(assets, dialogue_path).await?;
+
+        let mut portraits = HashMap::new();
+        for path in dialogue.lines().filter_map(|line| speaker.portrait_for(line)) {
+            if !portraits.contains_key(&*path) {
+                let texture = assets.load_texture(&path).await?;
+                portraits.insert(Box::from(path), texture);
+            }
+        }
+
+        Ok(Talker { dialogue, speaker, portraits: Arc::new(portraits), talks: 0, resume: None })
+    }
+}
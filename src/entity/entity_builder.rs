@@ -1,78 +1,84 @@
 use crate::prelude::*;
 
+/// Builds entities ready to be spawned into the [`EntityManager`], which is what assigns their ids.
+#[derive(Default)]
 pub struct EntityBuilder {
-    eid_count: usize
+    /// Loaded prefabs by name
+    prefabs: HashMap<Box<str>, Prefab>,
 }
 
 impl EntityBuilder {
     pub fn new() -> Self {
-        EntityBuilder { eid_count: 0 }
+        EntityBuilder { prefabs: HashMap::new() }
     }
 
     pub async fn build_entity_from_center(
-        &mut self,
+        &self,
+        assets: &AssetResolver,
         (x, y): (f32, f32),
         (bwidth, bheight): (f32, f32),
         draw_size: Vec2,
         rotation: f32,
         texture_path: &str,
     ) -> GResult<Entity> {
-        let e = Entity::build_from_center(
+        Entity::build_from_center(
+            assets,
             (x, y),
             (bwidth, bheight),
             draw_size,
             rotation,
             texture_path,
-            self.eid_count,
-        );
-        self.eid_count += 1;
-        e.await
+        ).await
     }
 
     pub async fn build_entity_from_boundary(
-        &mut self,
+        &self,
+        assets: &AssetResolver,
         boundary: Rect,
         draw_size: Vec2,
         rotation: f32,
         texture_path: &str,
     ) -> GResult<Entity> {
-        let e = Entity::build_from_boundary(
+        Entity::build_from_boundary(
+            assets,
             boundary,
             draw_size,
             rotation,
             texture_path,
-            self.eid_count,
-        );
-        self.eid_count += 1;
-        e.await
+        ).await
     }
 
-    pub async fn init_player(&mut self) -> GResult<Player> {
-        let p_entity = self.build_entity_from_center(
-            (0.0, 0.0),
-            (PLAYER_SIZE.x, PLAYER_SIZE.y),
-            PLAYER_SIZE,
-            0.0, 
-            PLAYER_SPRITE_PATH
-        ).await?;
-        Ok(Player::from_entity(p_entity))
+    /// Loads (or gets the cached) prefab with the given name, see [`Prefab`]
+    pub async fn prefab(&mut self, assets: &AssetResolver, name: &str) -> GResult<&Prefab> {
+        // Same deal as the geometry texture cache in Game::draw_map, no entry API with an async insert
+        if !self.prefabs.contains_key(name) {
+            let prefab = Prefab::load(assets, name).await?;
+            self.prefabs.insert(Box::from(name), prefab);
+        }
+        Ok(&self.prefabs[name])
+    }
+
+    /// Builds an entity from a prefab with per-instance overrides, e.g. `&[("position", "300, 360")]`
+    pub async fn build_from_prefab(&mut self, assets: &AssetResolver, name: &str, overrides: &[(&str, &str)]) -> GResult<Entity> {
+        let prefab = self.prefab(assets, name).await?.with_overrides(overrides)?;
+        prefab.build(assets).await
+    }
+
+    pub async fn init_player(&mut self, assets: &AssetResolver) -> GResult<Entity> {
+        self.build_from_prefab(assets, "player", &[]).await
     }
     
-    pub async fn init_npcs(&mut self) -> GResult<Vec<NPC>> {
-        let npc1_entity = self.build_entity_from_center(
-            (300.0, 360.0),
-            (70.0, 70.0), 
-            vec2(120.0, 120.0),
-            0.0,
-            "assets/sprites/test_npc.png",
-            
-        ).await?;
-        let npc1 = NPC::build_from_entity(
-            npc1_entity,
-            "assets/dialogue/test_dialogue.txt"
-        ).await?;
+    pub async fn init_npcs(&mut self, assets: &AssetResolver) -> GResult<Vec<Entity>> {
+        let npc1 = self.build_from_prefab(assets, "npc", &[
+            ("position", "300, 360"),
+            ("dialogue", "dialogue/test_dialogue.txt"),
+        ]).await?;
+        let npc2 = self.build_from_prefab(assets, "npc", &[
+            ("position", "600, 200"),
+            ("name", "%names.elder"),
+            ("dialogue", "dialogue/test_branching.dlg"),
+        ]).await?;
 
-        Ok(vec![npc1])
+        Ok(vec![npc1, npc2])
     }
 }
-
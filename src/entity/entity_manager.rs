@@ -1,30 +1,160 @@
 use crate::prelude::*;
 
+/// Generational entity id.
+///
+/// `index` is a storage slot that gets reused after its entity is despawned, while `generation` is bumped on every
+/// despawn, so an id held onto past its entity's lifetime never matches whatever gets spawned into that slot later.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct EntityId {
+    index: usize,
+    generation: u32,
+}
+
+impl EntityId {
+    /// Held by entities that were built but not yet spawned into an [`EntityManager`]; never alive.
+    pub const UNSPAWNED: EntityId = EntityId { index: usize::MAX, generation: u32::MAX };
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
 pub struct EntityManager {
-    pub player: Player,
-    pub npcs: Vec<NPC>,
+    player_id: EntityId,
+    /// Indexed by [`EntityId::index`]
+    entities: Vec<Option<Entity>>,
+    /// Current generation of each slot, indexed by [`EntityId::index`]
+    generations: Vec<u32>,
+    free_indices: Vec<usize>,
+    /// Despawns are deferred to [`EntityManager::flush_despawns`] so ids (and anything keyed on them, e.g. dialogue)
+    /// stay valid for the rest of the frame regardless of where in the frame they were requested.
+    pending_despawns: Vec<EntityId>,
 }
 
 impl EntityManager {
-    /// Draws all loaded/enabled entities
-    pub fn draw_loaded(&self) {
-        self.npcs.iter().for_each(NPC::draw);
-        self.player.draw();
+    pub fn new(player: Entity) -> Self {
+        let mut em = EntityManager {
+            player_id: EntityId::UNSPAWNED,
+            entities: vec![],
+            generations: vec![],
+            free_indices: vec![],
+            pending_despawns: vec![],
+        };
+        em.player_id = em.spawn(player);
+        em
     }
 
-    pub fn ref_player(&self) -> &Player {
-        &self.player
+    fn allocate_id(&mut self) -> EntityId {
+        if let Some(index) = self.free_indices.pop() {
+            EntityId { index, generation: self.generations[index] }
+        } else {
+            self.generations.push(0);
+            self.entities.push(None);
+            EntityId { index: self.generations.len() - 1, generation: 0 }
+        }
     }
 
-    pub fn mut_player(&mut self) -> &mut Player {
-        &mut self.player
+    /// Spawns an entity into the world immediately, assigning and returning its id.
+    pub fn spawn(&mut self, mut entity: Entity) -> EntityId {
+        let id = self.allocate_id();
+        entity.id = id;
+        self.entities[id.index] = Some(entity);
+        dlog!(Level::Debug, "Spawned entity {:?}", id);
+        id
     }
 
-    pub fn ref_npcs(&self) -> &[NPC] {
-        &self.npcs
+    /// Queues an entity for removal at the next [`EntityManager::flush_despawns`]. Until then it stays alive.
+    /// Stale ids and ids already queued are ignored. The player cannot be despawned.
+    pub fn despawn(&mut self, id: EntityId) {
+        if id == self.player_id {
+            dlog!(Level::Warn, "Tried to despawn the player {:?}, ignoring", id);
+        } else if self.is_alive(id) && !self.pending_despawns.contains(&id) {
+            self.pending_despawns.push(id);
+        }
     }
 
-    pub fn mut_npcs(&mut self) -> &mut [NPC] {
-        &mut self.npcs
+    /// Removes every entity queued with [`EntityManager::despawn`], returning their (now stale) ids.
+    pub fn flush_despawns(&mut self) -> Vec<EntityId> {
+        let despawned = std::mem::take(&mut self.pending_despawns);
+        for id in despawned.iter() {
+            self.entities[id.index] = None;
+            self.generations[id.index] = self.generations[id.index].wrapping_add(1);
+            self.free_indices.push(id.index);
+            dlog!(Level::Debug, "Despawned entity {:?}", id);
+        }
+        despawned
     }
-}
\ No newline at end of file
+
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        self.generations.get(id.index).is_some_and(|&generation| generation == id.generation)
+    }
+
+    /// Entities back to front: sorted by the bottom edge of their boundary so whoever's lower down is in front,
+    /// and the player in front of anyone level with them. Drawn by [`Game::draw_loaded_entites`] along with y-sorted geometry
+    pub fn draw_order(&self) -> Vec<&Entity> {
+        let mut order = self.iter().filter(|e| e.id != self.player_id).collect::<Vec<_>>();
+        order.push(self.ref_player());
+        // Stable, so the player stays last on ties
+        order.sort_by(|a, b| a.boundary().bottom().total_cmp(&b.boundary().bottom()));
+        order
+    }
+
+    pub fn player_id(&self) -> EntityId {
+        self.player_id
+    }
+
+    pub fn ref_player(&self) -> &Entity {
+        // The player can't be despawned, so this only fails while the player is detached
+        self.get(self.player_id).expect("player entity is always alive")
+    }
+
+    pub fn mut_player(&mut self) -> &mut Entity {
+        self.get_mut(self.player_id).expect("player entity is always alive")
+    }
+
+    /// Looks up an entity by id, None if it was despawned
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        self.entities[id.index].as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        self.entities[id.index].as_mut()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.iter().flatten()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
+        self.entities.iter_mut().flatten()
+    }
+
+    pub fn ids(&self) -> Vec<EntityId> {
+        self.iter().map(Entity::id).collect()
+    }
+
+    /// Temporarily takes an entity out of the manager so it can be mutated while looking at every other entity.
+    /// Must be given back with [`EntityManager::reattach`] before the end of the frame.
+    pub(crate) fn detach(&mut self, id: EntityId) -> Option<Entity> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        self.entities[id.index].take()
+    }
+
+    pub(crate) fn reattach(&mut self, entity: Entity) {
+        let index = entity.id.index;
+        debug_assert!(self.is_alive(entity.id) && self.entities[index].is_none(), "reattached entity wasn't detached");
+        self.entities[index] = Some(entity);
+    }
+}
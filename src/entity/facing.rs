@@ -0,0 +1,258 @@
+use crate::prelude::*;
+use std::str::FromStr;
+
+/// Which way an entity faces, and how that shows up on its sprite.
+///
+/// Follows whatever direction the entity moves (or looks) in via [`Facing::turn_toward`], or can be set outright with [`Facing::face`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Facing {
+    pub directions: FacingDirections,
+    pub sprites: FacingSprites,
+    direction: Direction,
+}
+
+/// How finely facing is tracked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FacingDirections {
+    #[default]
+    Four,
+    Eight,
+}
+
+/// How a [`Facing`] changes what gets drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FacingSprites {
+    /// Same sprite whichever way
+    #[default]
+    Fixed,
+    /// Sprite drawn facing right, mirrored when facing left at all
+    Flip,
+    /// One row of frames per direction, top to bottom in [`Direction::row`] order
+    Rows,
+}
+
+/// Compass direction in screen space (down is +y)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Sprites face down/toward the camera by default
+    #[default]
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+}
+
+impl Default for Facing {
+    fn default() -> Self {
+        Facing::new(FacingDirections::Four, FacingSprites::Fixed)
+    }
+}
+
+impl Facing {
+    pub fn new(directions: FacingDirections, sprites: FacingSprites) -> Self {
+        Facing { directions, sprites, direction: Direction::Down }
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Unit vector of [`Facing::direction`]
+    pub fn vector(&self) -> Vec2 {
+        self.direction.vector()
+    }
+
+    /// Faces `direction`, snapped to the nearest one we track
+    pub fn face(&mut self, direction: Direction) {
+        self.turn_toward(direction.vector());
+    }
+
+    /// Faces `direction` as is, even one we don't track, e.g. restoring a saved facing
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+    }
+
+    /// Faces the closest tracked direction to `toward`, zero leaves facing as is
+    pub fn turn_toward(&mut self, toward: Vec2) {
+        if let Some(direction) = Direction::nearest(toward, self.directions, self.direction) {
+            self.direction = direction;
+        }
+    }
+
+    /// Adjusts a sprite's draw params for facing. `texture_size` is in texture pixels
+    pub fn apply_to(&self, params: &mut DrawTextureParams, texture_size: Vec2) {
+        match self.sprites {
+            FacingSprites::Fixed => {}
+            FacingSprites::Flip => params.flip_x = self.direction.vector().x < 0.0,
+            FacingSprites::Rows => {
+                let rows = match self.directions {
+                    FacingDirections::Four => 4.0,
+                    FacingDirections::Eight => 8.0,
+                };
+                let mut source = params.source.unwrap_or(Rect::new(0.0, 0.0, texture_size.x, texture_size.y / rows));
+                source.y = self.direction.row(self.directions) as f32 * source.h;
+                params.source = Some(source);
+            }
+        }
+    }
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::Down,
+        Direction::DownLeft,
+        Direction::Left,
+        Direction::UpLeft,
+        Direction::Up,
+        Direction::UpRight,
+        Direction::Right,
+        Direction::DownRight,
+    ];
+
+    /// Name in data files, see the [`FromStr`] impl
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Down => "down",
+            Direction::DownLeft => "down_left",
+            Direction::Left => "left",
+            Direction::UpLeft => "up_left",
+            Direction::Up => "up",
+            Direction::UpRight => "up_right",
+            Direction::Right => "right",
+            Direction::DownRight => "down_right",
+        }
+    }
+
+    pub fn is_diagonal(self) -> bool {
+        matches!(self, Direction::DownLeft | Direction::UpLeft | Direction::UpRight | Direction::DownRight)
+    }
+
+    pub fn vector(self) -> Vec2 {
+        let v = match self {
+            Direction::Down => vec2(0.0, 1.0),
+            Direction::DownLeft => vec2(-1.0, 1.0),
+            Direction::Left => vec2(-1.0, 0.0),
+            Direction::UpLeft => vec2(-1.0, -1.0),
+            Direction::Up => vec2(0.0, -1.0),
+            Direction::UpRight => vec2(1.0, -1.0),
+            Direction::Right => vec2(1.0, 0.0),
+            Direction::DownRight => vec2(1.0, 1.0),
+        };
+        v.normalize()
+    }
+
+    /// Sprite sheet row for [`FacingSprites::Rows`]: down, left, right, up for four directions,
+    /// and going clockwise from down (as in [`Direction::ALL`]) for eight
+    pub fn row(self, directions: FacingDirections) -> usize {
+        match directions {
+            FacingDirections::Four => match self {
+                Direction::Down => 0,
+                Direction::Left => 1,
+                Direction::Right => 2,
+                Direction::Up => 3,
+                // Diagonals never get tracked in four-way, but go with the horizontal half just in case
+                Direction::DownLeft | Direction::UpLeft => 1,
+                Direction::UpRight | Direction::DownRight => 2,
+            },
+            FacingDirections::Eight => self as usize,
+        }
+    }
+
+    /// Closest direction to `v` out of the ones tracked, None for a zero vector.
+    /// Exact ties between two directions (e.g. diagonal input with four-way facing) keep `current` if it's one of them, so facing doesn't flicker.
+    pub fn nearest(v: Vec2, directions: FacingDirections, current: Direction) -> Option<Direction> {
+        let v = v.try_normalize()?;
+        let candidates = Direction::ALL.into_iter().filter(|d| directions == FacingDirections::Eight || !d.is_diagonal());
+        let mut best = None::<(Direction, f32)>;
+        for d in candidates {
+            let score = d.vector().dot(v);
+            let better = match best {
+                None => true,
+                Some((best_d, best_score)) => score > best_score + 1e-4 || (score >= best_score - 1e-4 && d == current && best_d != current),
+            };
+            if better {
+                best = Some((d, score));
+            }
+        }
+        best.map(|(d, _)| d)
+    }
+}
+
+/// Parses `4` or `8`
+impl FromStr for FacingDirections {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "4" => Ok(FacingDirections::Four),
+            "8" => Ok(FacingDirections::Eight),
+            _ => Err(format!("Unknown facing '{s}'. Valid values: 4 | 8")),
+        }
+    }
+}
+
+/// Parses `fixed`, `flip` or `rows`
+impl FromStr for FacingSprites {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(FacingSprites::Fixed),
+            "flip" => Ok(FacingSprites::Flip),
+            "rows" => Ok(FacingSprites::Rows),
+            _ => Err(format!("Unknown facing sprites '{s}'. Valid values: fixed | flip | rows")),
+        }
+    }
+}
+
+/// Parses e.g. `down` or `up_left`, see [`Direction::name`]
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Direction::ALL.into_iter().find(|d| d.name() == s).ok_or_else(|| format!(
+            "Unknown direction '{s}'. Valid directions: {:?}",
+            Direction::ALL.map(Direction::name)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_eight_way() {
+        let eight = |v| Direction::nearest(v, FacingDirections::Eight, Direction::Down);
+        assert_eq!(eight(vec2(1.0, 0.0)), Some(Direction::Right));
+        assert_eq!(eight(vec2(0.0, -3.0)), Some(Direction::Up));
+        assert_eq!(eight(vec2(-1.0, 1.0)), Some(Direction::DownLeft));
+        assert_eq!(eight(vec2(1.0, -0.3)), Some(Direction::Right));
+        assert_eq!(eight(vec2(1.0, -0.6)), Some(Direction::UpRight));
+        assert_eq!(eight(Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn nearest_four_way_keeps_current_on_ties() {
+        let four = |v, current| Direction::nearest(v, FacingDirections::Four, current);
+        assert_eq!(four(vec2(1.0, 0.2), Direction::Down), Some(Direction::Right));
+        assert_eq!(four(vec2(-1.0, 1.0), Direction::Left), Some(Direction::Left));
+        assert_eq!(four(vec2(-1.0, 1.0), Direction::Down), Some(Direction::Down));
+        // Neither half of the tie is current, so either's fine as long as it's not a diagonal
+        assert!(four(vec2(1.0, 1.0), Direction::Up).is_some_and(|d| d == Direction::Right || d == Direction::Down));
+    }
+
+    #[test]
+    fn rows_and_names() {
+        assert_eq!(Direction::Up.row(FacingDirections::Four), 3);
+        assert_eq!(Direction::DownRight.row(FacingDirections::Eight), 7);
+        for d in Direction::ALL {
+            assert_eq!(d.name().parse::<Direction>(), Ok(d));
+        }
+        assert!("north".parse::<Direction>().is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::prelude::*;
+
+/// Per-entity movement tuning, all in logical pixels and seconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MovementParams {
+    /// Top speed (px/s), horizontal only in platformer mode
+    pub max_speed: f32,
+    /// How fast velocity builds up toward top speed in the input direction (px/s²)
+    pub acceleration: f32,
+    /// How fast velocity bleeds off when there's no input (px/s²)
+    pub friction: f32,
+
+    // Platformer mode only, see [`PhysicsMode::Platformer`]
+    /// Downward acceleration (px/s²)
+    pub gravity: f32,
+    pub max_fall_speed: f32,
+    /// Upward velocity a jump starts with (px/s)
+    pub jump_speed: f32,
+    /// Fraction of `jump_speed` that rising velocity is capped to once jump is released, for variable jump height
+    pub jump_cut: f32,
+    /// Seconds after walking off a ledge that jumping still works
+    pub coyote_time: f32,
+    /// Seconds before landing that a jump press is remembered for
+    pub jump_buffer_time: f32,
+}
+
+impl Default for MovementParams {
+    fn default() -> Self {
+        MovementParams::from_max_speed(0.0)
+    }
+}
+
+/// Per-entity platformer bookkeeping
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlatformerState {
+    /// Standing on something as of the last collision pass
+    pub grounded: bool,
+    coyote_timer: f32,
+    jump_buffer_timer: f32,
+}
+
+impl MovementParams {
+    /// Reaches top speed in `1 / 8`s and stops in about as long, which feels decent for top-down walking
+    pub fn from_max_speed(max_speed: f32) -> Self {
+        MovementParams {
+            max_speed,
+            acceleration: max_speed * 8.0,
+            friction: max_speed * 8.0,
+            gravity: 2400.0,
+            max_fall_speed: 1200.0,
+            jump_speed: 900.0,
+            jump_cut: 0.4,
+            coyote_time: 0.1,
+            jump_buffer_time: 0.1,
+        }
+    }
+
+    /// Next frame's velocity given the current one and a desired direction.
+    /// `intent` longer than 1 (e.g. diagonal input) is normalized so diagonals aren't faster.
+    pub fn step_velocity(&self, velocity: Vec2, intent: Vec2, dt: f32) -> Vec2 {
+        let intent = if intent.length_squared() > 1.0 { intent.normalize() } else { intent };
+        if intent == Vec2::ZERO {
+            move_towards(velocity, Vec2::ZERO, self.friction * dt)
+        } else {
+            move_towards(velocity, intent * self.max_speed, self.acceleration * dt)
+        }
+    }
+}
+
+impl MovementParams {
+    /// Platformer counterpart of [`MovementParams::step_velocity`]; only the horizontal part of `intent` is used.
+    /// `jump_pressed`/`jump_held` are this frame's jump input, always false for entities without input.
+    pub fn step_platformer_velocity(
+        &self,
+        state: &mut PlatformerState,
+        velocity: Vec2,
+        intent: Vec2,
+        (jump_pressed, jump_held): (bool, bool),
+        dt: f32,
+    ) -> Vec2 {
+        let intent_x = intent.x.clamp(-1.0, 1.0);
+        let rate = if intent_x == 0.0 { self.friction } else { self.acceleration };
+        let vx = move_towards(vec2(velocity.x, 0.0), vec2(intent_x * self.max_speed, 0.0), rate * dt).x;
+        let mut vy = (velocity.y + self.gravity * dt).min(self.max_fall_speed);
+
+        state.coyote_timer = if state.grounded { self.coyote_time } else { state.coyote_timer - dt };
+        state.jump_buffer_timer = if jump_pressed { self.jump_buffer_time } else { state.jump_buffer_timer - dt };
+
+        if state.jump_buffer_timer > 0.0 && state.coyote_timer > 0.0 {
+            vy = -self.jump_speed;
+            state.jump_buffer_timer = 0.0;
+            state.coyote_timer = 0.0;
+            state.grounded = false;
+        }
+        if !jump_held && vy < 0.0 {
+            vy = vy.max(-self.jump_speed * self.jump_cut);
+        }
+
+        vec2(vx, vy)
+    }
+}
+
+/// Moves `from` toward `to` by at most `max_delta`, without overshooting
+fn move_towards(from: Vec2, to: Vec2, max_delta: f32) -> Vec2 {
+    let delta = to - from;
+    let distance = delta.length();
+    if distance <= max_delta || distance == 0.0 {
+        to
+    } else {
+        from + delta / distance * max_delta
+    }
+}
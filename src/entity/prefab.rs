@@ -0,0 +1,179 @@
+//! Entity prefab files, one per entity type under `prefabs/` (e.g. `prefabs/npc.prefab`), in the [`KvFile`] format.
+//!
+//! ```text
+//! # Required
+//! sprite = sprites/test_npc.png
+//! hitbox = 70, 70
+//! # Optional
+//! draw_size = 120, 120           # defaults to hitbox
+//! rotation = 0
+//! position = 300, 360            # center, usually given per instance as an override
+//! speed = 120                    # top speed
+//! acceleration = 960             # defaults to 8x speed
+//! friction = 960                 # defaults to 8x speed
+//! gravity = 2400                 # platformer maps only, as are the below
+//! max_fall_speed = 1200
+//! jump_speed = 900
+//! jump_cut = 0.4
+//! coyote_time = 0.1
+//! jump_buffer_time = 0.1
+//! animation = 64, 64, 4, 8       # frame width, frame height, frame count, fps
+//! facing = 4                     # 4 (default) or 8 directions
+//! facing_sprites = flip          # fixed (default) | flip | rows, see FacingSprites
+//! facing_direction = down        # initial facing, see Direction's FromStr impl
+//! controller = true              # driven by the keyboard
+//! collider = true
+//! collision_layer = npc          # defaults to world, see CollisionLayers' FromStr impl
+//! collision_mask = world|player  # defaults to all
+//! trigger = false                # only report overlaps, never block
+//! collider_shape = capsule       # rect (default) | circle | oriented_rect | capsule, fitted to the hitbox
+//! mass = 1                       # how hard to shove, relative to whatever's shoving
+//! immovable = false              # never shoved, like map geometry
+//! dialogue = dialogue/test_dialogue.txt
+//! name = %names.townsperson      # on the dialogue name plate, defaults to the prefab's name. `%key`s come from the string tables
+//! portrait = sprites/test_npc.png   # shown next to the dialogue
+//! behavior = wander 150          # see Behavior's FromStr impl
+//! face_player_when_talking = true
+//! interact = talk                # see InteractAction's FromStr impl
+//! interact_radius = 40           # defaults to DEFAULT_INTERACT_RADIUS
+//! ```
+//! Any key can be overridden per instance when building, see [`Prefab::with_overrides`].
+use crate::prelude::*;
+
+const PREFAB_KEYS: &[&str] = &[
+    "sprite", "hitbox", "draw_size", "rotation", "position", "speed", "acceleration", "friction", "animation",
+    "facing", "facing_sprites", "facing_direction",
+    "gravity", "max_fall_speed", "jump_speed", "jump_cut", "coyote_time", "jump_buffer_time",
+    "controller", "collider", "collision_layer", "collision_mask", "trigger", "collider_shape", "mass", "immovable", "dialogue", "name", "portrait", "behavior", "face_player_when_talking",
+    "interact", "interact_radius",
+];
+
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    kv: KvFile,
+    source: PrefabSource,
+}
+
+/// Which prefab (and overrides) an entity was built from, so it can be rebuilt, e.g. when loading a save
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrefabSource {
+    pub name: Box<str>,
+    pub overrides: Vec<(Box<str>, Box<str>)>,
+}
+
+impl Prefab {
+    pub async fn load(assets: &AssetResolver, name: &str) -> GResult<Self> {
+        let kv = KvFile::load(assets, &format!("{PREFABS_PATH}/{name}.prefab")).await?;
+        kv.check_keys(PREFAB_KEYS)?;
+        Ok(Prefab { kv, source: PrefabSource { name: Box::from(name), overrides: vec![] } })
+    }
+
+    /// Copy of this prefab with per-instance values taking priority, e.g. `&[("position", "300, 360")]`
+    pub fn with_overrides(&self, overrides: &[(&str, &str)]) -> GResult<Self> {
+        let mut kv = self.kv.clone();
+        let mut source = self.source.clone();
+        for (key, value) in overrides {
+            kv.set(key, value);
+            source.overrides.push((Box::from(*key), Box::from(*value)));
+        }
+        kv.check_keys(PREFAB_KEYS)?;
+        Ok(Prefab { kv, source })
+    }
+
+    pub fn kv(&self) -> &KvFile {
+        &self.kv
+    }
+
+    pub fn source(&self) -> &PrefabSource {
+        &self.source
+    }
+
+    pub async fn build(&self, assets: &AssetResolver) -> GResult<Entity> {
+        let kv = &self.kv;
+
+        let sprite = kv.require("sprite")?;
+        let hitbox = kv.parse_vec2(kv.require("hitbox")?)?;
+        let draw_size = kv.get_vec2("draw_size")?.unwrap_or(hitbox);
+        let rotation = kv.get_parsed::<f32>("rotation")?.unwrap_or(0.0);
+        let position = kv.get_vec2("position")?.unwrap_or_default();
+
+        let mut entity = Entity::build_from_center(
+            assets,
+            (position.x, position.y),
+            (hitbox.x, hitbox.y),
+            draw_size,
+            rotation,
+            &sprite.value,
+        ).await?;
+        entity.prefab = Some(self.source.clone());
+
+        let mut movement = MovementParams::from_max_speed(kv.get_parsed::<f32>("speed")?.unwrap_or(0.0));
+        for (key, param) in [
+            ("acceleration", &mut movement.acceleration),
+            ("friction", &mut movement.friction),
+            ("gravity", &mut movement.gravity),
+            ("max_fall_speed", &mut movement.max_fall_speed),
+            ("jump_speed", &mut movement.jump_speed),
+            ("jump_cut", &mut movement.jump_cut),
+            ("coyote_time", &mut movement.coyote_time),
+            ("jump_buffer_time", &mut movement.jump_buffer_time),
+        ] {
+            if let Some(value) = kv.get_parsed::<f32>(key)? {
+                *param = value;
+            }
+        }
+        entity = entity.with_movement(movement);
+        if let Some(entry) = kv.get("animation") {
+            match parse_floats(&entry.value).as_deref() {
+                Some(&[w, h, frame_count, fps]) => entity = entity.with_animation(Animation::new(vec2(w, h), frame_count as usize, fps)),
+                _ => return Err(kv.error(entry, "Bad value for 'animation', expected 'frame width, frame height, frame count, fps'")),
+            }
+        }
+        let mut facing = Facing::new(
+            kv.get_parsed::<FacingDirections>("facing")?.unwrap_or_default(),
+            kv.get_parsed::<FacingSprites>("facing_sprites")?.unwrap_or_default(),
+        );
+        if let Some(direction) = kv.get_parsed::<Direction>("facing_direction")? {
+            facing.face(direction);
+        }
+        entity = entity.with_facing(facing);
+        if kv.get_parsed::<bool>("controller")?.unwrap_or(false) {
+            entity = entity.with_controller(InputControl::default());
+        }
+        if kv.get_parsed::<bool>("collider")?.unwrap_or(false) {
+            let mut collider = Collider::default();
+            if let Some(layers) = kv.get_parsed::<CollisionLayers>("collision_layer")? {
+                collider.layers = layers;
+            }
+            if let Some(mask) = kv.get_parsed::<CollisionLayers>("collision_mask")? {
+                collider.mask = mask;
+            }
+            collider.trigger = kv.get_parsed::<bool>("trigger")?.unwrap_or(false);
+            collider.shape = kv.get_parsed::<ColliderShape>("collider_shape")?.unwrap_or_default();
+            if let Some(mass) = kv.get_parsed::<f32>("mass")? {
+                collider.mass = mass;
+            }
+            collider.immovable = kv.get_parsed::<bool>("immovable")?.unwrap_or(false);
+            entity = entity.with_collider(collider);
+        }
+        if let Some(dialogue) = kv.get("dialogue") {
+            let speaker = Speaker {
+                name: kv.get("name").map_or_else(|| self.source.name.clone(), |entry| entry.value.clone()),
+                portrait: kv.get("portrait").map(|entry| entry.value.clone()),
+            };
+            entity = entity.with_talker(Talker::load(assets, &dialogue.value, speaker).await?);
+        }
+        if let Some(mut behavior) = kv.get_parsed::<Behavior>("behavior")? {
+            if let Some(face_player_when_talking) = kv.get_parsed::<bool>("face_player_when_talking")? {
+                behavior = behavior.with_face_player_when_talking(face_player_when_talking);
+            }
+            entity = entity.with_behavior(behavior);
+        }
+        if let Some(action) = kv.get_parsed::<InteractAction>("interact")? {
+            let radius = kv.get_parsed::<f32>("interact_radius")?.unwrap_or(DEFAULT_INTERACT_RADIUS);
+            entity = entity.with_interactable(Interactable { radius, action });
+        }
+
+        Ok(entity)
+    }
+}
@@ -0,0 +1,239 @@
+//! Global game flags and variables: a typed key/value store anything can read or write (dialogue, triggers, entities...),
+//! e.g. `met_blacksmith = true` or `coins = 12`. Saved with the game.
+use crate::prelude::*;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FlagValue {
+    Bool(bool),
+    Int(i64),
+    Str(Box<str>),
+}
+
+/// A flag was set, changed or removed. Unset is None
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlagChange {
+    pub key: Box<str>,
+    pub old: Option<FlagValue>,
+    pub new: Option<FlagValue>,
+}
+
+/// See the [module docs](self). Changes queue up until [`Flags::drain_changes`] so whoever cares can react to them.
+#[derive(Clone, Debug, Default)]
+pub struct Flags {
+    /// Sorted so dumps and saves come out in a stable order
+    values: BTreeMap<Box<str>, FlagValue>,
+    changes: Vec<FlagChange>,
+}
+
+impl Flags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keys are written as is into saves, so stick to letters, digits and `_ . : -`
+    pub fn is_valid_key(key: &str) -> bool {
+        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || "_.:-".contains(c))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&FlagValue> {
+        self.values.get(key)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// None if unset or not a bool
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            FlagValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// None if unset or not an int
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.get(key)? {
+            FlagValue::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// None if unset or not a string
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            FlagValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Whether a bool flag is set to true, the usual check for one-off events
+    pub fn is_set(&self, key: &str) -> bool {
+        self.get_bool(key).unwrap_or(false)
+    }
+
+    /// Sets a flag, queueing a [`FlagChange`] if that changed anything. Invalid keys are logged and ignored
+    pub fn set(&mut self, key: &str, value: impl Into<FlagValue>) {
+        if !Flags::is_valid_key(key) {
+            dlog!(Level::Error, "Invalid flag key '{}', ignoring", key);
+            return;
+        }
+        let value = value.into();
+        if self.values.get(key) == Some(&value) {
+            return;
+        }
+        let old = self.values.insert(Box::from(key), value.clone());
+        self.changes.push(FlagChange { key: Box::from(key), old, new: Some(value) });
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some(old) = self.values.remove(key) {
+            self.changes.push(FlagChange { key: Box::from(key), old: Some(old), new: None });
+        }
+    }
+
+    /// Adds to an int flag (unset counts as 0), returning the new value.
+    /// A flag of another type gets overwritten.
+    pub fn add_int(&mut self, key: &str, amount: i64) -> i64 {
+        let value = self.get_int(key).unwrap_or(0) + amount;
+        self.set(key, value);
+        value
+    }
+
+    /// Drops every flag, e.g. before loading a save. Not reported as changes
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.changes.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FlagValue)> {
+        self.values.iter().map(|(key, value)| (&**key, value))
+    }
+
+    /// Takes every change since the last drain, oldest first
+    pub fn drain_changes(&mut self) -> impl Iterator<Item = FlagChange> + '_ {
+        self.changes.drain(..)
+    }
+}
+
+/// Debug dump, one `key = value` per line
+impl Display for Flags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.values.is_empty() {
+            return write!(f, "(no flags set)");
+        }
+        for (key, value) in self.iter() {
+            writeln!(f, "{key} = {value}")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<bool> for FlagValue {
+    fn from(b: bool) -> Self {
+        FlagValue::Bool(b)
+    }
+}
+
+impl From<i64> for FlagValue {
+    fn from(n: i64) -> Self {
+        FlagValue::Int(n)
+    }
+}
+
+impl From<&str> for FlagValue {
+    fn from(s: &str) -> Self {
+        FlagValue::Str(Box::from(s))
+    }
+}
+
+impl From<String> for FlagValue {
+    fn from(s: String) -> Self {
+        FlagValue::Str(s.into_boxed_str())
+    }
+}
+
+/// Typed, e.g. `bool:true`, `int:-3`, `str:some text`. Strings are escaped (see [`escape_value`]) so they come back
+/// exactly from a [`KvFile`] line, see the [`FromStr`](std::str::FromStr) impl for the way back
+impl Display for FlagValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlagValue::Bool(b) => write!(f, "bool:{b}"),
+            FlagValue::Int(n) => write!(f, "int:{n}"),
+            FlagValue::Str(s) => write!(f, "str:{}", escape_value(s)),
+        }
+    }
+}
+
+impl std::str::FromStr for FlagValue {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((kind, value)) = s.split_once(':') else {
+            return Err(format!("Flag value '{s}' is missing its type, expected e.g. 'bool:true', 'int:3' or 'str:text'"));
+        };
+        match kind {
+            "bool" => value.parse().map(FlagValue::Bool).map_err(|e| format!("Bad bool flag '{value}': {e}")),
+            "int" => value.parse().map(FlagValue::Int).map_err(|e| format!("Bad int flag '{value}': {e}")),
+            "str" => unescape_value(value).map(|s| FlagValue::Str(s.into_boxed_str())).map_err(|e| format!("Bad string flag: {e}")),
+            _ => Err(format!("Unknown flag type '{kind}'. Valid types: bool | int | str")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip_through_a_kv_file() {
+        let values = [
+            FlagValue::Bool(false),
+            FlagValue::Int(-42),
+            FlagValue::from(""),
+            FlagValue::from("plain text"),
+            FlagValue::from("#1 \\ hash"),
+            FlagValue::from("lines\nand\r\ncarriage\rreturns"),
+            FlagValue::from("  trailing and leading whitespace\t "),
+            FlagValue::from("str:nested"),
+        ];
+        for value in values {
+            let mut kv = KvFile::new("test");
+            kv.set("flag", &value);
+            let parsed = KvFile::parse("test", &kv.to_string()).unwrap();
+            assert_eq!(parsed.get_parsed::<FlagValue>("flag").unwrap(), Some(value), "{kv}");
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("true".parse::<FlagValue>().is_err());
+        assert!("bool:yes".parse::<FlagValue>().is_err());
+        assert!("int:1.5".parse::<FlagValue>().is_err());
+        assert!("float:1.5".parse::<FlagValue>().is_err());
+        assert!("str:bad \\q escape".parse::<FlagValue>().is_err());
+    }
+
+    #[test]
+    fn changes_queue_up() {
+        let mut flags = Flags::new();
+        flags.set("met", true);
+        flags.set("met", true);
+        assert_eq!(flags.add_int("coins", 3), 3);
+        assert_eq!(flags.add_int("coins", -1), 2);
+        flags.remove("met");
+        flags.remove("never_set");
+        flags.set("bad key!", 1);
+
+        let changes = flags.drain_changes().map(|c| (c.key, c.old, c.new)).collect::<Vec<_>>();
+        assert_eq!(changes, [
+            (Box::from("met"), None, Some(FlagValue::Bool(true))),
+            (Box::from("coins"), None, Some(FlagValue::Int(3))),
+            (Box::from("coins"), Some(FlagValue::Int(3)), Some(FlagValue::Int(2))),
+            (Box::from("met"), Some(FlagValue::Bool(true)), None),
+        ]);
+        assert!(!flags.contains("bad key!"));
+        assert_eq!(flags.drain_changes().count(), 0);
+    }
+}
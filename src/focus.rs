@@ -0,0 +1,88 @@
+//! Who gets input: gameplay, unless something modal (dialogue, a menu, a cutscene) has taken focus.
+//!
+//! Whatever has focus sits on a stack, the top one getting input and everything under it waiting its turn.
+//! While anything's on it the player can't move or interact, and the world is paused or keeps going without them,
+//! whichever is strictest of what's on the stack asks for (see [`WorldMode`]).
+use crate::prelude::*;
+
+/// Something that can take focus away from gameplay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FocusOwner {
+    Dialogue,
+    Menu,
+    Cutscene,
+}
+
+/// What the world does while something has focus
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WorldMode {
+    /// Nothing moves, thinks or animates
+    #[default]
+    Paused,
+    /// Everything carries on but the player, who stands still
+    Restricted,
+}
+
+/// See the [module docs](self)
+#[derive(Clone, Debug, Default)]
+pub struct Focus {
+    /// Bottom to top, each owner at most once
+    layers: Vec<(FocusOwner, WorldMode)>,
+}
+
+impl Focus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts `owner` on top, or just changes its mode if it's already on the stack
+    pub fn set(&mut self, owner: FocusOwner, mode: WorldMode) {
+        match self.layers.iter_mut().find(|(layer, _)| *layer == owner) {
+            Some((_, layer_mode)) => *layer_mode = mode,
+            None => self.layers.push((owner, mode)),
+        }
+    }
+
+    /// Takes `owner` off the stack, wherever it is in it
+    pub fn remove(&mut self, owner: FocusOwner) {
+        self.layers.retain(|(layer, _)| *layer != owner);
+    }
+
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Whatever's getting input, None for gameplay
+    pub fn top(&self) -> Option<FocusOwner> {
+        self.layers.last().map(|(owner, _)| *owner)
+    }
+
+    pub fn is_focused(&self, owner: FocusOwner) -> bool {
+        self.top() == Some(owner)
+    }
+
+    /// Whether the player can move and interact
+    pub fn gameplay_has_input(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Strictest mode on the stack, None while gameplay has focus
+    pub fn world(&self) -> Option<WorldMode> {
+        self.layers.iter().map(|(_, mode)| *mode).min_by_key(|mode| match mode {
+            WorldMode::Paused => 0,
+            WorldMode::Restricted => 1,
+        })
+    }
+}
+
+impl std::str::FromStr for WorldMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pause" => Ok(WorldMode::Paused),
+            "restrict" => Ok(WorldMode::Restricted),
+            _ => Err(format!("Unknown world mode '{s}'. Valid modes: pause | restrict")),
+        }
+    }
+}
@@ -26,7 +26,7 @@ impl TextureIndex {
         TextureIndex { space_index, texture_index, inner_index: maybe_inner_index }
     }
 
-    pub async fn load_texture(&self) -> GResult<(Texture2D, Option<DrawTextureParams>)> {
+    pub async fn load_texture(&self, assets: &AssetResolver) -> GResult<(Texture2D, Option<DrawTextureParams>)> {
         let mut path = String::from(ROOT_TEXTURES_PATH);
 
         let (space_dir, (texture_type, texture_file_name)) = match self.space_index.get() {
@@ -40,7 +40,7 @@ impl TextureIndex {
         path.push('/');
         path.push_str(texture_file_name);
 
-        let texture = load_texture(&path).await?;
+        let texture = assets.load_texture(&path).await?;
 
         let params = match texture_type {
             TextureType::Atlas(offset) => {
@@ -63,7 +63,7 @@ impl TextureIndex {
         Ok((texture, params))
     }
 
-    pub async fn load_player_texture(&self) -> GResult<(Texture2D, Option<DrawTextureParams>)> {
+    pub async fn load_player_texture(&self, _assets: &AssetResolver) -> GResult<(Texture2D, Option<DrawTextureParams>)> {
         todo!()
     }
 }
@@ -72,11 +72,19 @@ impl TextureIndex {
 pub struct Geometry {
     pub kind: GeometryType,
     pub t_index: Option<TextureIndex>,
+    pub collider: Collider,
+    /// Drawn depth sorted among entities (by bottom edge) instead of under all of them, for things you can walk behind
+    pub ysort: bool,
 }
 
 impl Geometry {
-    pub fn new_rect(x: f32, y: f32, w: f32, h: f32, t_index: Option<TextureIndex>) -> Self {
-        Geometry { kind: GeometryType::Rect(Rect::new(x, y, w, h)), t_index }
+    pub fn new_rect(x: f32, y: f32, w: f32, h: f32, t_index: Option<TextureIndex>, collider: Collider) -> Self {
+        Geometry { kind: GeometryType::Rect(Rect::new(x, y, w, h)), t_index, collider, ysort: false }
+    }
+
+    pub fn with_ysort(mut self, ysort: bool) -> Self {
+        self.ysort = ysort;
+        self
     }
 }
 
@@ -86,15 +94,27 @@ pub enum GeometryType {
     // is_wholly_inside_a_chunk: bool,
 }
 
-// impl RectBounded for Geometry {
-//     fn ref_boundary(&self) -> &Rect {
-//         &self.boundary
-//     }
+impl RectBounded for Geometry {
+    fn ref_boundary(&self) -> &Rect {
+        match &self.kind {
+            GeometryType::Rect(rect) => rect,
+        }
+    }
 
-//     fn mut_boundary(&mut self) -> &mut Rect {
-//         &mut self.boundary
-//     }
-// }
+    fn mut_boundary(&mut self) -> &mut Rect {
+        match &mut self.kind {
+            GeometryType::Rect(rect) => rect,
+        }
+    }
+}
+
+impl ShapeBounded for Geometry {
+    fn shape(&self) -> Shape {
+        match self.kind {
+            GeometryType::Rect(rect) => Shape::Rect(rect),
+        }
+    }
+}
 
 /* // REVIEW
 /// 256 x 256 logical pixels, contains a list of geometry that are either wholly or partially inside of it
@@ -109,7 +129,24 @@ pub struct GeometryMap {
 
 #[derive(Debug)]
 pub struct GeometryMap {
-    pub inner: Vec<Geometry>
+    pub inner: Vec<Geometry>,
+    pub settings: MapSettings,
+}
+
+/// Map-wide settings, given as `@setting value` lines in the map file
+#[derive(Debug, Default)]
+pub struct MapSettings {
+    pub physics: PhysicsMode,
+}
+
+/// Which movement model entities use on a map
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PhysicsMode {
+    /// WASD in any direction, see [`MovementParams::step_velocity`]
+    #[default]
+    TopDown,
+    /// Gravity and jumping, see [`MovementParams::step_platformer_velocity`]
+    Platformer,
 }
 
 // TODO //
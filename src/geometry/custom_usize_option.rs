@@ -22,8 +22,7 @@ impl CustomUsizeOption {
     }
 
     pub(super) fn is_none(&self) -> bool {
-        let val = unsafe { self.0.as_ptr().read() };
-        val == usize::MAX
+        *self == CustomUsizeOption::none()
     }
     #[inline]
     pub(super) fn is_some(&self) -> bool {
@@ -10,6 +10,13 @@ enum ParseType {
     Rect,
 }
 
+/// Per-geometry settings set by directives, see [`read_map_directives`]
+#[derive(Clone, Copy, Default)]
+struct GeometryAttributes {
+    collider: Collider,
+    ysort: bool,
+}
+
 struct MapFileParseError(usize, Option<String>, Option<Box<dyn Error>>);
 
 impl MapFileParseError {
@@ -49,13 +56,60 @@ impl Display for MapFileParseError {
 
 impl Error for MapFileParseError {}
 
+/// Pulls out `@setting value` directive lines into [`MapSettings`], blanking them so the geometry parser
+/// below never sees them (but line numbers in its errors stay right).
+///
+/// `@layer`, `@mask`, `@trigger` and `@ysort` apply to all geometry after them, so those come back as (line, attributes from then on) pairs.
+fn read_map_directives(file: &mut [u8]) -> GResult<(MapSettings, Vec<(usize, GeometryAttributes)>)> {
+    let mut settings = MapSettings::default();
+    let mut attributes = GeometryAttributes::default();
+    let mut attribute_changes = vec![];
+    for (i, line_bytes) in file.split_mut(|&b| b == b'\n').enumerate() {
+        if line_bytes.trim_ascii_start().first() != Some(&b'@') {
+            continue;
+        }
+        let line = String::from_utf8_lossy(line_bytes).into_owned();
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (setting, value) = line[1..].split_once(' ').map_or((&line[1..], ""), |(s, v)| (s, v.trim()));
+        match setting {
+            "physics" => settings.physics = match value {
+                "top_down" => PhysicsMode::TopDown,
+                "platformer" => PhysicsMode::Platformer,
+                _ => return Err(MapFileParseError::dyn_boxed(i + 1, format_args!("Unknown physics mode '{value}'. Valid values: {{ top_down | platformer }}."))),
+            },
+            "layer" | "mask" => {
+                let layers = value.parse::<CollisionLayers>().map_err(|e| MapFileParseError::dyn_boxed(i + 1, e))?;
+                if setting == "layer" {
+                    attributes.collider.layers = layers;
+                } else {
+                    attributes.collider.mask = layers;
+                }
+                attribute_changes.push((i + 1, attributes));
+            }
+            "trigger" => {
+                attributes.collider.trigger = value.parse::<bool>().map_err(|e| MapFileParseError::dyn_boxed_from_err(i + 1, e))?;
+                attribute_changes.push((i + 1, attributes));
+            }
+            "ysort" => {
+                attributes.ysort = value.parse::<bool>().map_err(|e| MapFileParseError::dyn_boxed_from_err(i + 1, e))?;
+                attribute_changes.push((i + 1, attributes));
+            }
+            _ => return Err(MapFileParseError::dyn_boxed(i + 1, format_args!("Unknown map setting '@{setting}'. Valid settings: {{ @physics | @layer | @mask | @trigger | @ysort }}."))),
+        }
+        line_bytes.fill(b' ');
+    }
+    Ok((settings, attribute_changes))
+}
+
+pub async fn read_map_file(assets: &AssetResolver, path: &str) -> GResult<GeometryMap> {
+    parse_map(assets.load_file(path).await?)
+}
+
 // Does the job for now
-pub async fn read_map_file(path: &str) -> GResult<GeometryMap> {
-    let file = {
-        let mut file = load_file(path).await?;
-        file.push(b'\n');
-        file
-    };
+/// Parses the contents of a map file, see [`read_map_file`]
+pub fn parse_map(mut file: Vec<u8>) -> GResult<GeometryMap> {
+    file.push(b'\n');
+    let (settings, attribute_changes) = read_map_directives(&mut file)?;
     let mut geometry = vec![];
 
     let (mut space_index, mut atlas_index, mut inner_index) = (None::<NonZeroUsize>, None::<usize>, None::<usize>);
@@ -65,6 +119,8 @@ pub async fn read_map_file(path: &str) -> GResult<GeometryMap> {
     let mut line = 1usize;
     let mut in_comment = false; 
     let mut curr_parse: Option<ParseType> = None;
+    // Whether the last field was closed with ')', so the newline after it doesn't count as an empty field
+    let mut closed = false;
 
     for byte in file {
         dlog!(Level::Trace, "Map Parser Iteration {} c:{:?} n:{:?}", byte as char, &c, &n.iter().map(|&b| b as char).collect::<Vec<_>>());
@@ -76,7 +132,7 @@ pub async fn read_map_file(path: &str) -> GResult<GeometryMap> {
             in_comment = true;
             continue;
         }
-        if i > 5 && byte != b'\n' && !in_comment {
+        if i > 6 && byte != b'\n' && !in_comment {
             return Err(MapFileParseError::dyn_boxed(line, "Too many input fields/commas/closing-parenthesis."));
         }
         if in_comment && byte != b'\n' {
@@ -86,7 +142,7 @@ pub async fn read_map_file(path: &str) -> GResult<GeometryMap> {
         // Non-trivial / Parse Logic Guards
         match byte {
             b')' | b',' | b'\n' => {
-                if curr_parse.is_some() && i < 6 {
+                if curr_parse.is_some() && i < 7 && !(byte == b'\n' && closed) {
                     // There's no real need to interpret any of these as a default value
                     // or anything, we can just reject it for simplicity in format.
                     if n.is_empty() {
@@ -113,6 +169,7 @@ pub async fn read_map_file(path: &str) -> GResult<GeometryMap> {
                     i += 1;
                     n.clear();
                 } 
+                closed = byte == b')';
 
                 // Attempts to finish line-parse if any, pushing finished geometry to collection Vec
                 if byte == b'\n' {
@@ -138,7 +195,15 @@ pub async fn read_map_file(path: &str) -> GResult<GeometryMap> {
                                     let inner_index = CustomUsizeOption::some(inner_index.expect("parser safety bullshit (ii)")); // SAFETY: We checked for non-usize::MAX above (inside this match block)
                                     TextureIndex::new(space_index, atlas_index, inner_index)
                                 });
-                                geometry.push(Geometry::new_rect(c[0], c[1], c[2], c[3], maybe_texture_index));
+                                // line was already bumped past this geometry's line above
+                                let attributes = attribute_changes.iter()
+                                    .rev()
+                                    .find(|&&(change_line, _)| change_line < line - 1)
+                                    .map_or_else(GeometryAttributes::default, |&(_, attributes)| attributes);
+                                geometry.push(
+                                    Geometry::new_rect(c[0], c[1], c[2], c[3], maybe_texture_index, attributes.collider)
+                                        .with_ysort(attributes.ysort)
+                                );
                             }
                         }
                         (space_index, atlas_index, inner_index) = (None, None, None);
@@ -163,6 +228,7 @@ pub async fn read_map_file(path: &str) -> GResult<GeometryMap> {
                 )),
             }
             n.push(byte);
+            closed = false;
         } else {
             curr_parse = match byte {
                 b'R' => ParseType::Rect,
@@ -175,7 +241,7 @@ pub async fn read_map_file(path: &str) -> GResult<GeometryMap> {
         }
     }
 
-    Ok(GeometryMap { inner: geometry })
+    Ok(GeometryMap { inner: geometry, settings })
 }
 
 /// ## Safety
@@ -187,4 +253,70 @@ where
     <Out as FromStr>::Err: Error + 'static
 {
     std::str::from_utf8_unchecked(input.as_ref()).parse::<Out>().map_err(|e| MapFileParseError::dyn_boxed_from_err(line, e))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> GResult<GeometryMap> {
+        parse_map(src.as_bytes().to_vec())
+    }
+
+    fn rect(geometry: &Geometry) -> Rect {
+        match geometry.kind {
+            GeometryType::Rect(rect) => rect,
+        }
+    }
+
+    #[test]
+    fn untextured_rects() {
+        let map = parse("# comment\nR(50, 50, 10, 10)\nR(-1.5, 2, 3, 4) # trailing comment").unwrap();
+        assert_eq!(map.inner.len(), 2);
+        assert_eq!(rect(&map.inner[0]), Rect::new(50.0, 50.0, 10.0, 10.0));
+        assert_eq!(rect(&map.inner[1]), Rect::new(-1.5, 2.0, 3.0, 4.0));
+        assert!(map.inner.iter().all(|g| g.t_index.is_none()));
+    }
+
+    // All 7 fields used to trip the too many fields check, and the last one never got read
+    #[test]
+    fn textured_rect_takes_all_seven_fields() {
+        let map = parse("R(-300, 100, 64, 64, 2, 1, 3)\n").unwrap();
+        let expected = TextureIndex::new(NonZeroUsize::new(2).unwrap(), 1, unsafe { CustomUsizeOption::some(3) });
+        assert_eq!(map.inner[0].t_index, Some(expected));
+        assert_eq!(rect(&map.inner[0]), Rect::new(-300.0, 100.0, 64.0, 64.0));
+    }
+
+    #[test]
+    fn field_count_errors() {
+        let too_many = parse("R(1, 2, 3, 4, 2, 0, 0, 9)").unwrap_err().to_string();
+        assert!(too_many.contains("Too many input fields"), "{too_many}");
+        let no_inner = parse("R(1, 2, 3, 4, 2, 0)").unwrap_err().to_string();
+        assert!(no_inner.contains("Missing inner_index"), "{no_inner}");
+        let no_atlas = parse("R(1, 2, 3, 4, 2)").unwrap_err().to_string();
+        assert!(no_atlas.contains("Missing atlas_index"), "{no_atlas}");
+        assert!(parse("R(1, 2, 3)").is_err());
+        assert!(parse("R(1, , 3, 4)").is_err());
+    }
+
+    #[test]
+    fn directives() {
+        let map = parse("@physics platformer\nR(0, 0, 1, 1)\n@ysort true\n@layer npc|player\n@trigger true # comment\nR(0, 0, 2, 2)").unwrap();
+        assert_eq!(map.settings.physics, PhysicsMode::Platformer);
+        let (before, after) = (&map.inner[0], &map.inner[1]);
+        assert!(!before.ysort && !before.collider.trigger);
+        assert_eq!(before.collider.layers, CollisionLayers::WORLD);
+        assert!(after.ysort && after.collider.trigger);
+        assert_eq!(after.collider.layers, CollisionLayers::NPC | CollisionLayers::PLAYER);
+        assert_eq!(parse("R(0, 0, 1, 1)").unwrap().settings.physics, PhysicsMode::TopDown);
+    }
+
+    #[test]
+    fn directive_errors_point_at_their_line() {
+        let unknown = parse("R(0, 0, 1, 1)\n@gravity 3").unwrap_err().to_string();
+        assert!(unknown.starts_with("[Line #2]") && unknown.contains("Unknown map setting '@gravity'"), "{unknown}");
+        let bad_mode = parse("@physics sideways").unwrap_err().to_string();
+        assert!(bad_mode.starts_with("[Line #1]") && bad_mode.contains("Unknown physics mode"), "{bad_mode}");
+        assert!(parse("@layer walls").is_err());
+        assert!(parse("@ysort maybe").is_err());
+    }
+}
@@ -11,25 +11,29 @@ pub fn window_conf() -> Conf {
 
 impl Game {
     pub async fn init() -> GResult<Game> {
+        let assets = AssetResolver::with_default_roots();
+
         let mut eb = EntityBuilder::new();
-        let player = eb.init_player().await?;
-        let npcs = eb.init_npcs().await?;
+        let player = eb.init_player(&assets).await?;
+        let npcs = eb.init_npcs(&assets).await?;
 
-        let em = EntityManager { player, npcs };
+        let mut em = EntityManager::new(player);
+        for npc in npcs {
+            em.spawn(npc);
+        }
 
-        let font = load_ttf_font(DEFAULT_FONT_TTF_PATH).await?;
-        let dm = DialogueManager::from_text_params(
-            OwnedTextParams {
-                font: Some(font),
-                font_size: DEFAULT_FONT_SIZE,
-                color: DEFAULT_FONT_COLOR,
-            }
-        );
+        let language = std::env::var("GAME_LANG").unwrap_or_else(|_| DEFAULT_LANGUAGE.to_owned());
+        let loc = Localization::load(&assets, &language).await?;
+        // Fonts depend on the language, see localization
+        let dm = DialogueManager::from_text_params(loc.load_text_params(&assets).await?);
 
         let pset = PSet::current();
 
-        let test_map = read_map_file("assets/maps/test_map").await?;
+        let map_path = std::env::var("GAME_MAP").unwrap_or_else(|_| START_MAP_PATH.to_owned());
+        let map = read_map_file(&assets, &map_path).await?;
+        let map_path = map_path.into_boxed_str();
+        let saves = SaveSlots::new(SAVES_DIR);
 
-        Ok(Game { eb, em, dm, pset, map: test_map, geometry_textures: HashMap::new(), })
+        Ok(Game { assets, eb, em, dm, pset, map, map_path, saves, flags: Flags::new(), loc, focus: Focus::new(), notice: None, geometry_textures: HashMap::new(), interaction_target: None, collisions: collision::CollisionWorld::new(), })
     }
 }
\ No newline at end of file
@@ -0,0 +1,87 @@
+//! Player interaction with the world: whatever [`Interactable`] is closest in front of the player gets highlighted,
+//! and pressing [`INTERACT_KEY`] triggers its [`InteractAction`].
+use crate::prelude::*;
+
+impl Game {
+    /// Picks this frame's interaction target and triggers it if the interact key was pressed.
+    /// Nothing can be interacted with while something else has input (see [`crate::focus`]), so no swapping NPCs mid-conversation
+    pub(crate) fn handle_interaction(&mut self) {
+        self.interrupt_distant_dialogue();
+        self.interaction_target = match self.focus.gameplay_has_input() {
+            true => find_interaction_target(&self.em),
+            false => None,
+        };
+
+        if !is_key_pressed(INTERACT_KEY) {
+            return;
+        }
+        let Some(target) = self.interaction_target.and_then(|id| self.em.get(id)) else {
+            return;
+        };
+        let Some(interactable) = target.interactable.as_ref() else {
+            return;
+        };
+        match interactable.action {
+            InteractAction::Talk => {
+                self.dm.load_dialogue(target, &self.loc);
+                // Face each other, AI keeps the target looking at the player after this (see Behavior::face_player_when_talking)
+                let (target_id, target_position) = (target.id, target.position());
+                let player = self.em.mut_player();
+                let to_target = target_position - player.position();
+                player.facing.turn_toward(to_target);
+                if let Some(target) = self.em.get_mut(target_id) {
+                    target.facing.turn_toward(-to_target);
+                }
+                self.handle_dialogue_events();
+            }
+        }
+    }
+
+    /// Ensure in player camera [`camera::set_player_camera`] for proper behavior.
+    pub fn draw_interaction_highlight(&self) {
+        if let Some(target) = self.interaction_target.and_then(|id| self.em.get(id)) {
+            let tl = target.position() - target.draw_size / 2.0;
+            draw_rectangle_lines(tl.x, tl.y, target.draw_size.x, target.draw_size.y, 3.0, INTERACT_HIGHLIGHT_COLOR);
+        }
+    }
+
+    /// Interrupts the conversation if the player and whoever they're talking to have got [`DIALOGUE_LEAVE_DISTANCE`] apart
+    fn interrupt_distant_dialogue(&mut self) {
+        let Some(talking_to) = self.dm.loaded_eid().and_then(|eid| self.em.get(eid)) else {
+            return;
+        };
+        if boundary_gap(&self.em.ref_player().boundary, &talking_to.boundary) > DIALOGUE_LEAVE_DISTANCE {
+            self.dm.interrupt();
+        }
+    }
+}
+
+/// Closest interactable within its interaction radius of the player and roughly in the direction the player is facing
+pub fn find_interaction_target(em: &EntityManager) -> Option<EntityId> {
+    let player = em.ref_player();
+    let facing = player.facing.vector();
+
+    em.iter()
+        .filter(|e| e.id != player.id)
+        .filter_map(|e| {
+            let interactable = e.interactable.as_ref()?;
+            let gap = boundary_gap(&player.boundary, &e.boundary);
+            if gap > interactable.radius {
+                return None;
+            }
+            let to_target = e.position() - player.position();
+            if to_target != Vec2::ZERO && facing.angle_between(to_target).abs() > INTERACT_HALF_ANGLE {
+                return None;
+            }
+            Some((e.id, gap))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(id, _)| id)
+}
+
+/// Shortest distance between two rects, 0 if they touch or overlap
+fn boundary_gap(a: &Rect, b: &Rect) -> f32 {
+    let dx = (a.left() - b.right()).max(b.left() - a.right()).max(0.0);
+    let dy = (a.top() - b.bottom()).max(b.top() - a.bottom()).max(0.0);
+    vec2(dx, dy).length()
+}
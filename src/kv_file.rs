@@ -0,0 +1,310 @@
+//! Dead simple `key = value` file format shared by the game's data files (prefabs, etc.).
+//!
+//! One entry per line, `#` starts a comment, blank lines are ignored, whitespace around keys and values is trimmed.
+//! A key appearing more than once is allowed and the last one wins, which is what makes overrides trivial.
+//!
+//! Values that need `#` or whitespace at their ends kept (anything the game writes itself, like saves) go through
+//! [`escape_value`] and [`KvFile::unescaped`].
+use std::str::FromStr;
+
+use crate::prelude::*;
+
+pub struct KvParseError {
+    /// What was being parsed, usually the asset path
+    source: Box<str>,
+    /// 0 when the error isn't about any one line, e.g. a missing key
+    line: usize,
+    msg: String,
+}
+
+impl KvParseError {
+    pub fn dyn_boxed<T: ToString>(source: &str, line: usize, msg: T) -> Box<dyn Error> {
+        Box::new(KvParseError { source: Box::from(source), line, msg: msg.to_string() })
+    }
+}
+
+// Using Display formatting for Debug's impl as well, same as the map parser's error
+impl Debug for KvParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for KvParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.line == 0 {
+            write!(f, "[{}] {}", self.source, self.msg)
+        } else {
+            write!(f, "[{} Line #{}] {}", self.source, self.line, self.msg)
+        }
+    }
+}
+
+impl Error for KvParseError {}
+
+#[derive(Clone, Debug)]
+pub struct KvEntry {
+    pub key: Box<str>,
+    pub value: Box<str>,
+    /// Line the entry came from, 0 if it was set in code
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct KvFile {
+    /// What was parsed, for errors
+    pub source: Box<str>,
+    entries: Vec<KvEntry>,
+}
+
+impl KvFile {
+    pub fn new(source: &str) -> Self {
+        KvFile { source: Box::from(source), entries: vec![] }
+    }
+
+    pub fn parse(source: &str, text: &str) -> GResult<Self> {
+        let mut file = KvFile::new(source);
+        for (i, raw_line) in text.lines().enumerate() {
+            let line = raw_line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(KvParseError::dyn_boxed(source, i + 1, format_args!("Expected 'key = value', got '{line}'")));
+            };
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(KvParseError::dyn_boxed(source, i + 1, "Missing key before '='"));
+            }
+            file.entries.push(KvEntry { key: Box::from(key), value: Box::from(value.trim()), line: i + 1 });
+        }
+        Ok(file)
+    }
+
+    pub async fn load(assets: &AssetResolver, path: &str) -> GResult<Self> {
+        KvFile::parse(path, &assets.load_string(path).await?)
+    }
+
+    /// Last entry with the given key
+    pub fn get(&self, key: &str) -> Option<&KvEntry> {
+        self.entries.iter().rev().find(|entry| &*entry.key == key)
+    }
+
+    /// Appends an entry, taking priority over any earlier one with the same key
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        self.entries.push(KvEntry { key: Box::from(key), value: value.to_string().into_boxed_str(), line: 0 });
+    }
+
+    pub fn entries(&self) -> &[KvEntry] {
+        &self.entries
+    }
+
+    /// Errors on the first key not in `known`, to catch typos in data files
+    pub fn check_keys(&self, known: &[&str]) -> GResult<()> {
+        match self.entries.iter().find(|entry| !known.contains(&&*entry.key)) {
+            Some(entry) => Err(self.error(entry, format_args!("Unknown key '{}'. Valid keys: {:?}", entry.key, known))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn error<T: ToString>(&self, entry: &KvEntry, msg: T) -> Box<dyn Error> {
+        KvParseError::dyn_boxed(&self.source, entry.line, msg)
+    }
+
+    pub fn require(&self, key: &str) -> GResult<&KvEntry> {
+        self.get(key).ok_or_else(|| KvParseError::dyn_boxed(&self.source, 0, format_args!("Missing required key '{key}'")))
+    }
+
+    pub fn parse_value<T>(&self, entry: &KvEntry) -> GResult<T>
+    where
+        T: FromStr,
+        <T as FromStr>::Err: Display,
+    {
+        entry.value.parse::<T>().map_err(|e| self.error(entry, format_args!("Bad value '{}' for '{}': {e}", entry.value, entry.key)))
+    }
+
+    /// Parses an optional value, None if the key is absent
+    pub fn get_parsed<T>(&self, key: &str) -> GResult<Option<T>>
+    where
+        T: FromStr,
+        <T as FromStr>::Err: Display,
+    {
+        self.get(key).map(|entry| self.parse_value(entry)).transpose()
+    }
+
+    /// Parses a `x, y` value
+    pub fn parse_vec2(&self, entry: &KvEntry) -> GResult<Vec2> {
+        match parse_floats(&entry.value).as_deref() {
+            Some(&[x, y]) => Ok(vec2(x, y)),
+            _ => Err(self.error(entry, format_args!("Bad value '{}' for '{}', expected 'x, y'", entry.value, entry.key))),
+        }
+    }
+
+    pub fn get_vec2(&self, key: &str) -> GResult<Option<Vec2>> {
+        self.get(key).map(|entry| self.parse_vec2(entry)).transpose()
+    }
+
+    /// Value written with [`escape_value`], as it was before
+    pub fn unescaped(&self, entry: &KvEntry) -> GResult<Box<str>> {
+        unescape_value(&entry.value)
+            .map(String::into_boxed_str)
+            .map_err(|e| self.error(entry, format_args!("Bad value '{}' for '{}': {e}", entry.value, entry.key)))
+    }
+
+    pub fn entries_mut(&mut self) -> &mut [KvEntry] {
+        &mut self.entries
+    }
+}
+
+/// Writes the entries back out in the same format, e.g. for files written by the game itself like saves.
+/// Values are written as is, so any that might have `#` in them or whitespace at their ends need [`escape_value`] first.
+impl Display for KvFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in self.entries.iter() {
+            writeln!(f, "{} = {}", entry.key, entry.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Comma separated floats, None if any fail to parse
+pub fn parse_floats(s: &str) -> Option<Vec<f32>> {
+    s.split(',').map(|n| n.trim().parse::<f32>().ok()).collect()
+}
+
+/// Escapes a value so [`unescape_value`] gets it back exactly, as written raw anything after a `#` is lost to a comment
+/// and whitespace at either end is trimmed. `\\`, `\h` (`#`), `\n` and `\r` are always escaped,
+/// other whitespace only at the ends, as `\s` (space), `\t` or `\u{hex}`
+pub fn escape_value(s: &str) -> String {
+    // Whitespace outside start..end would be trimmed
+    let (start, end) = (s.len() - s.trim_start().len(), s.trim_end().len());
+
+    let mut out = String::with_capacity(s.len());
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '#' => out.push_str("\\h"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if (i < start || i >= end) && c.is_whitespace() => match c {
+                ' ' => out.push_str("\\s"),
+                '\t' => out.push_str("\\t"),
+                c => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Undoes [`escape_value`]
+pub fn unescape_value(s: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('h') => out.push('#'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('s') => out.push(' '),
+            Some('t') => out.push('\t'),
+            Some('u') => {
+                let rest = chars.as_str();
+                let code = rest.strip_prefix('{')
+                    .and_then(|rest| rest.split_once('}'))
+                    .and_then(|(hex, _)| Some((hex.len(), char::from_u32(u32::from_str_radix(hex, 16).ok()?)?)));
+                let Some((hex_len, c)) = code else {
+                    return Err(String::from("Bad escape '\\u', expected e.g. '\\u{a0}'"));
+                };
+                out.push(c);
+                chars = rest[hex_len + 2..].chars();
+            }
+            other => return Err(format!(
+                "Bad escape '\\{}'. Valid escapes: \\\\ | \\h | \\n | \\r | \\s | \\t | \\u{{hex}}",
+                other.map(String::from).unwrap_or_default()
+            )),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let kv = KvFile::parse("test", "# comment\n\nname = Old Man  # trailing\n  speed=1.5\nname = Elder\nempty =\nsum = 1 = 1\n").unwrap();
+        assert_eq!(kv.entries().len(), 5);
+        assert_eq!(&*kv.get("name").unwrap().value, "Elder");
+        assert_eq!(kv.get("name").unwrap().line, 5);
+        assert_eq!(kv.get_parsed::<f32>("speed").unwrap(), Some(1.5));
+        assert_eq!(&*kv.get("empty").unwrap().value, "");
+        assert_eq!(&*kv.get("sum").unwrap().value, "1 = 1");
+        assert!(kv.get("missing").is_none());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let no_equals = KvFile::parse("test", "a = 1\njust words").unwrap_err().to_string();
+        assert_eq!(no_equals, "[test Line #2] Expected 'key = value', got 'just words'");
+        assert!(KvFile::parse("test", " = 3").is_err());
+    }
+
+    #[test]
+    fn values() {
+        let kv = KvFile::parse("test", "pos = 1, -2.5\nbad_pos = 1\nspeed = fast\n").unwrap();
+        assert_eq!(kv.get_vec2("pos").unwrap(), Some(vec2(1.0, -2.5)));
+        assert!(kv.get_vec2("bad_pos").is_err());
+        assert_eq!(kv.get_vec2("none").unwrap(), None);
+        let bad = kv.get_parsed::<f32>("speed").unwrap_err().to_string();
+        assert!(bad.starts_with("[test Line #3] Bad value 'fast' for 'speed'"), "{bad}");
+        assert!(kv.require("none").unwrap_err().to_string().contains("Missing required key 'none'"));
+    }
+
+    #[test]
+    fn check_keys() {
+        let kv = KvFile::parse("test", "name = a\nsped = 3").unwrap();
+        assert!(kv.check_keys(&["name", "sped"]).is_ok());
+        assert!(kv.check_keys(&["name", "speed"]).unwrap_err().to_string().contains("Unknown key 'sped'"));
+    }
+
+    #[test]
+    fn display_parses_back() {
+        let mut kv = KvFile::new("test");
+        kv.set("a", 1);
+        kv.set("b", "two words");
+        let parsed = KvFile::parse("test", &kv.to_string()).unwrap();
+        assert_eq!(parsed.entries().iter().map(|e| (&*e.key, &*e.value)).collect::<Vec<_>>(), [("a", "1"), ("b", "two words")]);
+    }
+
+    #[test]
+    fn escaped_values_round_trip() {
+        for value in ["plain", "", " ", "  padded\t", "a # comment?", "back\\slash", "two\nlines\r\n", "\u{a0}nbsp\u{2003}", "mid  dle\tspace", "\\h literally"] {
+            let mut kv = KvFile::new("test");
+            kv.set("key", escape_value(value));
+            let parsed = KvFile::parse("test", &kv.to_string()).unwrap();
+            assert_eq!(&*parsed.unescaped(parsed.require("key").unwrap()).unwrap(), value, "{kv}");
+        }
+    }
+
+    #[test]
+    fn only_the_ends_get_whitespace_escaped() {
+        assert_eq!(escape_value(" a b\tc "), "\\sa b\tc\\s");
+        assert_eq!(escape_value("#1"), "\\h1");
+    }
+
+    #[test]
+    fn bad_escapes() {
+        assert!(unescape_value("\\q").is_err());
+        assert!(unescape_value("trailing\\").is_err());
+        assert!(unescape_value("\\u{zz}").is_err());
+        assert!(unescape_value("\\u{a0").is_err());
+        assert_eq!(unescape_value("\\u{a0}x").unwrap(), "\u{a0}x");
+    }
+}
@@ -8,10 +8,22 @@ pub mod camera;
 pub mod window_drawing;
 pub mod geometry;
 pub mod traits;
+pub mod assets;
+pub mod dialogue;
+pub mod kv_file;
+pub mod interaction;
+pub mod collision;
+pub mod shape;
+pub mod save;
+pub mod flags;
+pub mod localization;
+pub mod focus;
 
 use crate::prelude::*;
 
 pub struct Game {
+    /// Every asset load goes through here, see [`AssetResolver`]
+    pub assets: AssetResolver,
     pub eb: EntityBuilder,
     pub em: EntityManager,
     pub dm: DialogueManager,
@@ -19,8 +31,24 @@ pub struct Game {
     pub pset: PSet,
     /// Current loaded map
     pub map: GeometryMap,
+    /// Asset path `map` was loaded from
+    pub map_path: Box<str>,
+    /// Where saves go, see [`save`]
+    pub saves: SaveSlots,
     /// Current loaded geometry textures
     pub geometry_textures: HashMap<TextureIndex, (Texture2D, Option<DrawTextureParams>)>,
+    /// Interactable the player would interact with this frame, see [`interaction`]
+    pub interaction_target: Option<EntityId>,
+    /// Game flags and variables, see [`flags`]
+    pub flags: Flags,
+    /// Strings in the current language, see [`localization`]
+    pub loc: Localization,
+    /// Whatever's taken input away from gameplay, see [`focus`]
+    pub focus: Focus,
+    /// Message in the corner and when it went up, see [`Game::show_notice`]
+    pub notice: Option<(Box<str>, f64)>,
+    /// Resolves entity movement, see [`collision::CollisionWorld`]
+    pub collisions: collision::CollisionWorld,
 }
 
 impl Game {
@@ -44,82 +72,199 @@ impl Game {
         let pset = &self.pset;
         camera::set_ui_camera(pset);
         
-        if let Some((dpart, maybe_params)) = self.dm.handle_dialogue() {
+        let mut host = GameDialogueHost { flags: &mut self.flags, em: &mut self.em };
+        let has_focus = self.focus.is_focused(FocusOwner::Dialogue);
+        if let Some(view) = self.dm.handle_dialogue(pset, &mut host, has_focus) {
             window_drawing::draw_dialogue_frame(pset);
-            window_drawing::draw_dialogue_text(pset, dpart, maybe_params);
+            if let Some(portrait) = view.portrait {
+                window_drawing::draw_dialogue_portrait(pset, portrait);
+            }
+            window_drawing::draw_dialogue_name_plate(pset, view.speaker, view.text_params.clone());
+            window_drawing::draw_dialogue_text(pset, &view);
+            window_drawing::draw_dialogue_choices(pset, &view.choices, view.selected, view.text_params);
+        }
+
+        if self.notice.as_ref().is_some_and(|(_, since)| get_time() - since > NOTICE_SECONDS) {
+            self.notice = None;
+        }
+        if let Some((text, _)) = self.notice.as_ref() {
+            window_drawing::draw_notice(pset, text, self.dm.get_text_params());
         }
+
+        self.handle_dialogue_events();
+    }
+
+    /// Shows a short message in the corner for [`NOTICE_SECONDS`], replacing any already up
+    pub fn show_notice(&mut self, text: impl Into<Box<str>>) {
+        self.notice = Some((text.into(), get_time()));
     }
 }
 
 impl Game {
     pub fn init_player_view_and_update_entites(&mut self) {
         self.handle_entity_updates_and_collisions();
+        self.handle_interaction();
         camera::set_player_camera(&self.pset, self.em.ref_player().position());
         // draw_rectangle_lines(0.0, 0.0, LOGICAL_WIDTH, LOGICAL_HEIGHT, 1.0, BLACK);
     }
 
+    /// Draws entities and y-sorted geometry together, whatever's lower down (by bottom edge) in front
     pub fn draw_loaded_entites(&self) {
-        self.em.draw_loaded();
+        enum DepthSorted<'a> {
+            Entity(&'a Entity),
+            Geometry(&'a Geometry),
+        }
+
+        // Stable sort, so on ties geometry goes behind entities and entities keep their own draw order
+        let mut sorted = self.map.inner.iter()
+            .filter(|g| g.ysort)
+            .map(|g| (g.boundary().bottom(), DepthSorted::Geometry(g)))
+            .chain(self.em.draw_order().into_iter().map(|e| (e.boundary().bottom(), DepthSorted::Entity(e))))
+            .collect::<Vec<_>>();
+        sorted.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        for (_, drawn) in sorted {
+            match drawn {
+                DepthSorted::Entity(entity) => entity.draw(),
+                DepthSorted::Geometry(geometry) => self.draw_geometry(geometry),
+            }
+        }
+        self.draw_interaction_highlight();
     }
 
+    /// Skipped entirely while the world's paused, see [`focus`]
     fn handle_entity_updates_and_collisions(&mut self) {
+        if self.focus.world() == Some(WorldMode::Paused) {
+            return;
+        }
         let dt = get_frame_time();
-        let player = &mut self.em.player;
-        let npcs = &mut self.em.npcs;
-
-        player.update_x(dt);
-        for npc in npcs.iter_mut() {
-            if player.overlaps_excluding_bounds(npc) {
-                if player.ref_velocity().x.is_sign_positive() {
-                    player.move_by_origin_to(vec2(npc.ref_boundary().left() - player.bsize().x, player.ref_boundary().y));
+        let player_has_input = self.focus.gameplay_has_input();
+        let player_position = self.em.ref_player().position();
+        let talking_to = self.dm.loaded_eid();
+
+        let movers = self.em.iter().filter(|e| e.is_mover()).map(Entity::id).collect::<Vec<_>>();
+        for id in movers {
+            let Some(mover) = self.em.get_mut(id) else {
+                continue;
+            };
+
+            if let Some(controller) = mover.controller.as_mut() {
+                if player_has_input {
+                    controller.poll();
                 } else {
-                    player.move_by_origin_to(vec2(npc.ref_boundary().right(), player.ref_boundary().y));
+                    controller.release();
                 }
-                self.dm.load_dialogue(npc);
             }
+            let position = mover.position();
+            if let Some(behavior) = mover.behavior.as_mut() {
+                behavior.think(position, &BehaviorContext { player_position, in_dialogue: talking_to == Some(id) }, dt);
+            }
+
+            match self.map.settings.physics {
+                PhysicsMode::TopDown => mover.update_velocity(dt),
+                PhysicsMode::Platformer => mover.update_platformer_velocity(dt),
+            }
+            mover.update_facing();
         }
 
-        // for geometry in self.map.
+        self.collisions.step(&mut self.em, &self.map, dt);
+        self.handle_collision_events();
+        self.handle_flag_changes();
 
-        player.update_y(dt);
-        for npc in npcs.iter_mut() {
-            if player.overlaps_excluding_bounds(npc) {
-                if player.ref_velocity().y.is_sign_positive() {
-                    player.move_by_origin_to(vec2(player.ref_boundary().x, npc.ref_boundary().top() - player.bsize().y));
-                } else {
-                    player.move_by_origin_to(vec2(player.ref_boundary().x, npc.ref_boundary().bottom()));
+        for animation in self.em.iter_mut().filter_map(|e| e.animation.as_mut()) {
+            animation.update(dt);
+        }
+
+        for eid in self.em.flush_despawns() {
+            self.dm.unload_dialogue_of(eid);
+        }
+    }
+}
+
+impl Game {
+    /// Where game code reacts to flags changing, see [`Flags::set`]
+    fn handle_flag_changes(&mut self) {
+        for FlagChange { key, old, new } in self.flags.drain_changes() {
+            dlog!(Level::Debug, "Flag '{}' changed: {:?} -> {:?}", key, old, new);
+        }
+    }
+
+    /// Logs every flag on [`FLAGS_DUMP_KEY`]
+    pub fn handle_debug_keys(&mut self) {
+        if is_key_pressed(FLAGS_DUMP_KEY) {
+            dlog!(Level::Info, "FLAGS\n{}", self.flags);
+        }
+    }
+}
+
+impl Game {
+    /// Where game code reacts to conversations starting and stopping, keeping each [`Talker`]'s memory of them
+    /// and giving dialogue [`focus`] for as long as there's any loaded
+    fn handle_dialogue_events(&mut self) {
+        for event in self.dm.drain_events() {
+            dlog!(Level::Debug, "{:?}", event);
+            let (DialogueEvent::Started { eid, .. } | DialogueEvent::Interrupted { eid, .. } | DialogueEvent::Finished { eid }) = event;
+            let Some(talker) = self.em.get_mut(eid).and_then(|entity| entity.talker.as_mut()) else {
+                continue;
+            };
+            match event {
+                DialogueEvent::Started { resumed, .. } => {
+                    if !resumed {
+                        talker.talks += 1;
+                    }
+                    talker.resume = None;
                 }
-                self.dm.load_dialogue(npc);
+                DialogueEvent::Interrupted { index, .. } => talker.resume = Some(index),
+                DialogueEvent::Finished { .. } => (),
             }
         }
+        match self.dm.world_mode() {
+            Some(mode) => self.focus.set(FocusOwner::Dialogue, mode),
+            None => self.focus.remove(FocusOwner::Dialogue),
+        }
+    }
+}
+
+impl Game {
+    /// Where game code reacts to what the [`collision::CollisionWorld`] saw this frame
+    fn handle_collision_events(&mut self) {
+        for event in self.collisions.drain_events() {
+            dlog!(Level::Debug, "{:?}", event);
+        }
     }
 }
 
 impl Game {
     /// Ensure in player camera [`camera::set_player_camera`] for proper behavior.
+    /// Only draws geometry that isn't y-sorted, the rest is drawn among the entities by [`Game::draw_loaded_entites`].
     pub async fn draw_map(&mut self) -> GResult<()> {
-        for geometry in self.map.inner.iter() {
-            if let Some(t_index) = geometry.t_index.as_ref() {
-                match geometry.kind {
-                    GeometryType::Rect(Rect { x, y, .. }) => {
-                        // I don't think entry API here is workable, even with an out pointer for the error, as we need to pass an async function,
-                        // so this pattern is required instead -- It's more sensible for our case with the Err anyways.
-                        let (texture, params) = if self.geometry_textures.contains_key(&t_index) {
-                            &self.geometry_textures[&t_index]
-                        } else {
-                            let _ = self.geometry_textures.insert(*t_index, t_index.load_texture().await?);
-                            &self.geometry_textures[&t_index]
-                        };
-
-                        if let Some(params) = params {
-                            draw_texture_ex(texture, x, y, BLACK, params.clone());
-                        } else {
-                            draw_texture(texture, x, y, BLACK);
-                        }
-                    }
-                }
+        // I don't think entry API here is workable, even with an out pointer for the error, as we need to pass an async function,
+        // so this pattern is required instead -- It's more sensible for our case with the Err anyways.
+        // Y-sorted geometry's textures get loaded here too so drawing it later doesn't need to be async.
+        for t_index in self.map.inner.iter().filter_map(|g| g.t_index.as_ref()) {
+            if !self.geometry_textures.contains_key(t_index) {
+                let _ = self.geometry_textures.insert(*t_index, t_index.load_texture(&self.assets).await?);
             }
         }
+
+        for geometry in self.map.inner.iter().filter(|g| !g.ysort) {
+            self.draw_geometry(geometry);
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Textures must already be loaded, see [`Game::draw_map`]
+    fn draw_geometry(&self, geometry: &Geometry) {
+        match (&geometry.kind, geometry.t_index.and_then(|t_index| self.geometry_textures.get(&t_index))) {
+            (&GeometryType::Rect(Rect { x, y, .. }), Some((texture, params))) => {
+                if let Some(params) = params {
+                    draw_texture_ex(texture, x, y, BLACK, params.clone());
+                } else {
+                    draw_texture(texture, x, y, BLACK);
+                }
+            }
+            // Untextured geometry is still solid, so make it visible
+            (&GeometryType::Rect(Rect { x, y, w, h }), None) => draw_rectangle_lines(x, y, w, h, 2.0, DARKGRAY),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Text shown to the player, looked up by key in per-language string tables so it can be translated.
+//!
+//! Each language is a [`KvFile`] at `lang/<code>.lang`, e.g. `lang/en.lang`:
+//! ```text
+//! language.name = English
+//! # Optional, defaulting to DEFAULT_FONT_TTF_PATH, DEFAULT_ITALIC_FONT_TTF_PATH and DEFAULT_FONT_SIZE.
+//! # A font without an italic one keeps italics upright
+//! language.font = fonts/IM_Fell_English/IMFellEnglish-Regular.ttf
+//! language.italic_font = fonts/IM_Fell_English/IMFellEnglish-Italic.ttf
+//! language.font_size = 50
+//! ui.saved = Game saved
+//! elder.greeting = Well met...{pause 0.4} traveller.
+//! ```
+//! Strings can have dialogue markup (see [`MarkedText`]), but not `#` as that starts a comment, so no `{color #rrggbb}` either.
+//!
+//! Dialogue scripts pull text in with `%key` (see [`DialogueScript::localize`]), as can speaker names anywhere they're given.
+//! Keys missing from the current language fall back to [`DEFAULT_LANGUAGE`]'s with a warning when the language loads,
+//! and keys missing from that too show up as the key itself.
+//!
+//! [`LANGUAGE_KEY`] cycles through [`LANGUAGES`], `GAME_LANG` picks the one to start in.
+use crate::prelude::*;
+
+/// UI strings the game itself uses, checked for on load so missing ones get warned about up front
+pub const UI_STRING_KEYS: &[&str] = &[UI_SAVED, UI_LOADED, UI_LANGUAGE];
+pub const UI_SAVED: &str = "ui.saved";
+pub const UI_LOADED: &str = "ui.loaded";
+pub const UI_LANGUAGE: &str = "ui.language";
+
+/// One language's strings, see the [module docs](self)
+#[derive(Clone, Debug, Default)]
+pub struct StringTable {
+    /// Language code, e.g. `en`
+    pub code: Box<str>,
+    strings: HashMap<Box<str>, Box<str>>,
+    /// From `language.font_size`
+    font_size: Option<u16>,
+}
+
+impl StringTable {
+    pub fn path(code: &str) -> String {
+        format!("{LANG_PATH}/{code}.lang")
+    }
+
+    pub async fn load(assets: &AssetResolver, code: &str) -> GResult<Self> {
+        Self::from_kv(code, &KvFile::load(assets, &Self::path(code)).await?)
+    }
+
+    pub fn from_kv(code: &str, kv: &KvFile) -> GResult<Self> {
+        let strings = kv.entries().iter().map(|entry| (entry.key.clone(), entry.value.clone())).collect();
+        Ok(StringTable { code: Box::from(code), strings, font_size: kv.get_parsed("language.font_size")? })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(|s| &**s)
+    }
+
+    /// Keys in `other` this doesn't have, sorted. Settings under `language.` don't count, they're all optional
+    fn missing_from<'a>(&self, other: &'a StringTable) -> Vec<&'a str> {
+        let mut missing = other.strings.keys()
+            .filter(|key| !key.starts_with("language.") && !self.strings.contains_key(*key))
+            .map(|key| &**key)
+            .collect::<Vec<_>>();
+        missing.sort_unstable();
+        missing
+    }
+}
+
+/// The current language's strings, backed by another's, normally [`DEFAULT_LANGUAGE`]
+#[derive(Clone, Debug, Default)]
+pub struct Localization {
+    current: StringTable,
+    /// None when the current language is the default
+    fallback: Option<StringTable>,
+}
+
+impl Localization {
+    /// Loads a language along with the default one to fall back on, see [`Localization::new`]
+    pub async fn load(assets: &AssetResolver, code: &str) -> GResult<Self> {
+        let current = StringTable::load(assets, code).await?;
+        let fallback = match code {
+            DEFAULT_LANGUAGE => None,
+            _ => Some(StringTable::load(assets, DEFAULT_LANGUAGE).await?),
+        };
+        Ok(Localization::new(current, fallback))
+    }
+
+    /// Warns about whatever `current` is missing, UI strings and strings `fallback` has
+    pub fn new(current: StringTable, fallback: Option<StringTable>) -> Self {
+        for key in UI_STRING_KEYS.iter().filter(|key| current.get(key).is_none()) {
+            dlog!(Level::Warn, "Language '{}' is missing UI string '{}'", current.code, key);
+        }
+        if let Some(fallback) = fallback.as_ref() {
+            let missing = current.missing_from(fallback);
+            if !missing.is_empty() {
+                dlog!(Level::Warn, "Language '{}' is missing {} strings, falling back to '{}' for: {:?}",
+                    current.code, missing.len(), fallback.code, missing
+                );
+            }
+        }
+        Localization { current, fallback }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.current.code
+    }
+
+    /// Name of the current language in itself, e.g. `Français`
+    pub fn language_name(&self) -> &str {
+        self.current.get("language.name").unwrap_or(&self.current.code)
+    }
+
+    /// Falls back to the default language, None if neither has it
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.current.get(key).or_else(|| self.fallback.as_ref()?.get(key))
+    }
+
+    /// String for `key`, or the key itself if there isn't one
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.get(key).unwrap_or(key)
+    }
+
+    /// `%key` looked up (see [`Localization::text`]), anything else as is. For names and such that might be either
+    pub fn resolve<'a>(&'a self, s: &'a str) -> &'a str {
+        match s.strip_prefix('%') {
+            Some(key) => self.text(key),
+            None => s,
+        }
+    }
+
+    /// String for `key` with its markup parsed, or the key itself (with a warning) if there isn't one or the markup's bad
+    pub fn marked(&self, key: &str) -> MarkedText {
+        let parsed = match self.get(key) {
+            Some(s) => MarkedText::parse(s),
+            None => Err(String::from("No string for it in any language")),
+        };
+        parsed.unwrap_or_else(|e| {
+            dlog!(Level::Warn, "[{}] '{}': {}", StringTable::path(self.code()), key, e);
+            MarkedText { text: Box::from(key), ..Default::default() }
+        })
+    }
+
+    /// Dialogue text params in the language's fonts, see the [module docs](self)
+    pub async fn load_text_params(&self, assets: &AssetResolver) -> GResult<OwnedTextParams> {
+        let (font_path, italic_path) = match self.current.get("language.font") {
+            Some(font) => (font, self.current.get("language.italic_font")),
+            None => (DEFAULT_FONT_TTF_PATH, Some(DEFAULT_ITALIC_FONT_TTF_PATH)),
+        };
+        let font = assets.load_ttf_font(font_path).await?;
+        let italic_font = match italic_path {
+            Some(path) => Some(assets.load_ttf_font(path).await?),
+            None => None,
+        };
+        Ok(OwnedTextParams {
+            font: Some(font),
+            italic_font,
+            font_size: self.current.font_size.unwrap_or(DEFAULT_FONT_SIZE),
+            color: DEFAULT_FONT_COLOR,
+        })
+    }
+}
+
+impl Game {
+    /// Switches every bit of text over to another language, including any dialogue that's open.
+    /// Nothing changes if it fails.
+    pub async fn set_language(&mut self, code: &str) -> GResult<()> {
+        let loc = Localization::load(&self.assets, code).await?;
+        let text_params = loc.load_text_params(&self.assets).await?;
+        self.loc = loc;
+        self.dm.set_text_params(text_params);
+        self.dm.relocalize(&self.loc);
+        Ok(())
+    }
+
+    /// Cycles through [`LANGUAGES`] on [`LANGUAGE_KEY`]. Failures are logged rather than ending the game
+    pub async fn handle_language_keys(&mut self) {
+        if !is_key_pressed(LANGUAGE_KEY) {
+            return;
+        }
+        let next = LANGUAGES.iter().position(|code| *code == self.loc.code()).map_or(0, |i| (i + 1) % LANGUAGES.len());
+        match self.set_language(LANGUAGES[next]).await {
+            Ok(()) => self.show_notice(format!("{}: {}", self.loc.text(UI_LANGUAGE), self.loc.language_name())),
+            Err(e) => dlog!(Level::Error, "Switching language failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(code: &str, text: &str) -> StringTable {
+        StringTable::from_kv(code, &KvFile::parse(&StringTable::path(code), text).unwrap()).unwrap()
+    }
+
+    fn example() -> Localization {
+        Localization::new(
+            table("fr", "language.name = Français\nlanguage.font_size = 40\nui.saved = Partie sauvegardée\nelder.greeting = Bonjour{pause 0.5} toi\nbroken = {nope}\n"),
+            Some(table("en", "ui.saved = Game saved\nui.loaded = Game loaded\nelder.ask = Ask\n")),
+        )
+    }
+
+    #[test]
+    fn string_tables() {
+        let fr = table("fr", "language.font_size = 40\nui.saved = Partie sauvegardée # comment\n");
+        assert_eq!(fr.get("ui.saved"), Some("Partie sauvegardée"));
+        assert_eq!(fr.get("ui.loaded"), None);
+        assert_eq!(fr.font_size, Some(40));
+        assert_eq!(StringTable::path("fr"), format!("{LANG_PATH}/fr.lang"));
+        assert!(StringTable::from_kv("fr", &KvFile::parse("fr.lang", "language.font_size = big\n").unwrap()).is_err());
+        // Language settings are all optional, so aren't missing
+        assert_eq!(table("de", "a = 1\n").missing_from(&table("en", "language.name = English\na = 1\nb = 2\nc = 3\n")), ["b", "c"]);
+    }
+
+    #[test]
+    fn falling_back() {
+        let loc = example();
+        assert_eq!(loc.code(), "fr");
+        assert_eq!(loc.language_name(), "Français");
+        assert_eq!(loc.get("ui.saved"), Some("Partie sauvegardée"));
+        assert_eq!(loc.get("ui.loaded"), Some("Game loaded"));
+        assert_eq!(loc.get("nowhere"), None);
+        assert_eq!(loc.text("elder.ask"), "Ask");
+        assert_eq!(loc.text("nowhere"), "nowhere");
+        // Without a name it goes by its code
+        assert_eq!(Localization::new(table("de", ""), None).language_name(), "de");
+    }
+
+    #[test]
+    fn resolving_names() {
+        let loc = example();
+        assert_eq!(loc.resolve("%ui.saved"), "Partie sauvegardée");
+        assert_eq!(loc.resolve("%nowhere"), "nowhere");
+        assert_eq!(loc.resolve("Old Man"), "Old Man");
+    }
+
+    #[test]
+    fn marked_strings() {
+        let loc = example();
+        let greeting = loc.marked("elder.greeting");
+        assert_eq!(&*greeting.text, "Bonjour toi");
+        assert_eq!(greeting.markers, [(7, TextMarker::Pause(0.5))]);
+        assert_eq!(&*loc.marked("elder.ask").text, "Ask");
+        // Missing or with bad markup, the key shows instead
+        assert_eq!(loc.marked("nowhere"), MarkedText { text: Box::from("nowhere"), ..Default::default() });
+        assert_eq!(&*loc.marked("broken").text, "broken");
+    }
+
+    #[test]
+    fn shipped_languages_are_complete() {
+        let assets = AssetResolver::new(["assets"]);
+        let load = |code| {
+            let path = StringTable::path(code);
+            let text = std::fs::read_to_string(assets.resolve(&path).unwrap()).unwrap();
+            StringTable::from_kv(code, &KvFile::parse(&path, &text).unwrap()).unwrap()
+        };
+        let default = load(DEFAULT_LANGUAGE);
+        for code in LANGUAGES {
+            let table = load(code);
+            assert_eq!(table.missing_from(&default), Vec::<&str>::new(), "{code}");
+            for key in UI_STRING_KEYS {
+                assert!(table.get(key).is_some(), "{code} is missing {key}");
+            }
+        }
+    }
+}
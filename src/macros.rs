@@ -13,14 +13,20 @@
 /// just walk through the level scale*.
 /// ## Some working examples
 /// ```
-/// dlog!(Level::Trace)
-/// dlog!(Level::Debug, "Literal")
-/// dlog!(Level::Info, vec![0, 1, 2, 3])
-/// dlog!(Level::Warn, String::new(), vec![], variable)
+/// # use game::dlog;
+/// # use log::Level;
+/// # let (variable, dpi_scale, zoom, lb_offsets, lb_scales) = (0, 2.0, 1.5, (0.0, 0.0), (1.0, 1.0));
+/// dlog!(Level::Trace);
+/// dlog!(Level::Debug, "Literal");
+/// dlog!(Level::Info, vec![0, 1, 2, 3]);
+/// dlog!(Level::Warn, String::new(), Vec::<u8>::new(), variable);
 /// dlog!(Level::Error, dpi_scale, zoom, "test", lb_offsets, lb_scales); 
 /// ```
 /// ## Some non-working examples
-/// ```
+/// ```compile_fail
+/// # use game::dlog;
+/// # use log::Level;
+/// # let (dpi_scale, zoom, lb_offsets, lb_scales) = (2.0, 1.5, (0.0, 0.0), (1.0, 1.0));
 /// dlog!(Level::Trace, 1);
 /// dlog!(Level::Debug, dpi_scale, "Foo {:?} {:?}", lb_offsets, lb_scales);
 /// dlog!(Level::Info, dpi_scale, zoom, 1, lb_offsets, lb_scales);
@@ -5,9 +5,9 @@ async fn main() -> GResult<()> {
     env_logger::try_init()?;
 
     let mut g = Game::init().await?;
-    dlog!(Level::Info, "ENTITY IDS\n\tPLAYER: {:?}\n\tNPCS: {:?}",
-        g.em.ref_player().id(), 
-        g.em.ref_npcs().iter().map(NPC::id).collect::<Vec<_>>()
+    dlog!(Level::Info, "ENTITY IDS\n\tPLAYER: {:?}\n\tALL: {:?}",
+        g.em.player_id(), 
+        g.em.ids()
     );
 
 
@@ -17,6 +17,9 @@ async fn main() -> GResult<()> {
         g.draw_map().await?; // Currently will lazy-load textures -- not sure if will keep this implementation
         g.draw_loaded_entites();
         g.handle_ui();
+        g.handle_save_keys().await;
+        g.handle_language_keys().await;
+        g.handle_debug_keys();
         g.next_frame().await;
     }
 }
\ No newline at end of file
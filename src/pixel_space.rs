@@ -1,7 +1,7 @@
 //! ## Some definitions
 //! - Logical Pixels (LP) -> our game's "logical" pixel-space as defined in src/constants.rs
 //! - Physical Pixels (PP) -> a window manager's representation of the game's window in terms of actual pixels
-//!     on the screen, accounting for any dpi scaling
+//!   on the screen, accounting for any dpi scaling
 //! - Macroquad Natural Pixels (NP) -> Macroquad's internal "logical" pixel-space which is just `physical pixels / dpi_scale`
 //! 
 //! We use logical pixels for most draw calls in the game including UI.
@@ -5,10 +5,19 @@ pub use crate::init::*;
 pub use crate::pixel_space::*;
 pub use crate::geometry::*;
 pub use crate::traits::*;
+pub use crate::assets::*;
+pub use crate::dialogue::*;
+pub use crate::kv_file::*;
+pub use crate::shape::*;
+pub use crate::save::*;
+pub use crate::flags::*;
+pub use crate::localization::*;
+pub use crate::focus::*;
 
 // Crate Modules
 pub use crate::window_drawing;
 pub use crate::camera;
+pub use crate::collision;
 
 // Crate Items
 pub use crate::Game;
@@ -24,4 +33,4 @@ pub use std::mem::MaybeUninit;
 pub use std::error::Error;
 pub use std::fmt::{ Debug, Display };
 pub use std::num::NonZeroUsize;
-pub use std::collections::HashMap;
\ No newline at end of file
+pub use std::collections::{ HashMap, HashSet };
\ No newline at end of file
@@ -0,0 +1,529 @@
+//! Save files: a snapshot of the game ([`SaveData`]) written in the [`KvFile`] format to numbered slots under [`SAVES_DIR`].
+//!
+//! ```text
+//! version = 6
+//! map = maps/test_map
+//! entity.0.prefab = player
+//! entity.0.player = true
+//! entity.0.position = 640, 360
+//! entity.0.velocity = 0, 0
+//! entity.0.facing = down
+//! entity.1.prefab = npc
+//! entity.1.override.dialogue = dialogue/test_dialogue.txt
+//! entity.1.talks = 2            # conversations had, only for talkers that have been talked to
+//! entity.1.resume = 5           # node an interrupted conversation picks up at, see Talker::resume
+//! entity.1.destination = 300, 200   # only for entities sent walking by dialogue, see DialogueEffect::Move
+//! ...
+//! dialogue.entity = 1           # index of the entity above, only while in dialogue
+//! dialogue.index = 2
+//! flag.met_blacksmith = bool:true   # see FlagValue's Display impl
+//! ```
+//! Entities are rebuilt from their prefab (see [`PrefabSource`]) on load, so only what changes while playing is saved.
+//! The map, prefab names and override values are escaped (see [`escape_value`]) so they come back exactly.
+//! Entities not built from a prefab aren't saved at all.
+//!
+//! Older saves are brought up to date on load by [`MIGRATIONS`], so whenever the format changes bump [`SAVE_VERSION`] and add one.
+use crate::prelude::*;
+use std::{ collections::BTreeMap, fs, io, path::PathBuf };
+
+/// Version written into new saves
+pub const SAVE_VERSION: u32 = 6;
+
+/// Upgrades a save by one version, `MIGRATIONS[0]` takes a version 1 save to version 2 and so on.
+/// Must have `SAVE_VERSION - 1` entries.
+const MIGRATIONS: &[fn(&mut KvFile)] = &[
+    // 1 -> 2: added flags, version 1 saves just don't have any
+    |_| {},
+    // 2 -> 3: added talk counts, missing ones are 0
+    |_| {},
+    // 3 -> 4: added interrupted conversations, version 3 saves just don't have any
+    |_| {},
+    // 4 -> 5: strings are escaped, so escape the ones that weren't. Whatever a '#' cut off is gone for good
+    |kv| {
+        for entry in kv.entries_mut() {
+            if is_escaped_key(&entry.key) {
+                entry.value = escape_value(&entry.value).into_boxed_str();
+            }
+        }
+    },
+    // 5 -> 6: added destinations, version 5 saves lost them so entities go back to what their prefab does
+    |_| {},
+];
+
+/// Keys holding strings run through [`escape_value`]
+fn is_escaped_key(key: &str) -> bool {
+    key == "map" || key.strip_prefix("entity.").is_some_and(|rest| {
+        rest.split_once('.').is_some_and(|(_, field)| field == "prefab" || field.starts_with("override."))
+    })
+}
+
+/// Everything needed to put the game back how it was
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SaveData {
+    pub map_path: Box<str>,
+    /// In spawn order, the player's somewhere among them
+    pub entities: Vec<EntitySave>,
+    pub dialogue: Option<DialogueSave>,
+    /// Sorted by key, see [`Flags`]
+    pub flags: Vec<(Box<str>, FlagValue)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntitySave {
+    pub prefab: PrefabSource,
+    pub player: bool,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub facing: Direction,
+    /// See [`Talker::talks`]
+    pub talks: u32,
+    /// See [`Talker::resume`]
+    pub resume: Option<usize>,
+    /// Where dialogue sent it walking to (see [`DialogueEffect::Move`]), replacing whatever behavior its prefab gives it.
+    /// Other behaviors come from the prefab, so start over on load like the rest of its AI
+    pub destination: Option<Vec2>,
+}
+
+/// Dialogue in progress
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DialogueSave {
+    /// Index into [`SaveData::entities`]
+    pub entity: usize,
+    pub index: usize,
+}
+
+impl EntitySave {
+    pub fn capture(entity: &Entity, prefab: PrefabSource, player: bool) -> Self {
+        EntitySave {
+            prefab,
+            player,
+            position: entity.position(),
+            velocity: entity.velocity,
+            facing: entity.facing.direction(),
+            talks: entity.talker.as_ref().map_or(0, |talker| talker.talks),
+            resume: entity.talker.as_ref().and_then(|talker| talker.resume),
+            destination: match entity.behavior.as_ref().map(|behavior| &behavior.kind) {
+                Some(BehaviorKind::GoTo { destination }) => Some(*destination),
+                _ => None,
+            },
+        }
+    }
+
+    /// Puts the saved state onto an entity freshly built from [`EntitySave::prefab`]
+    pub fn apply_to(&self, entity: &mut Entity) {
+        entity.move_by_center_to(self.position);
+        entity.velocity = self.velocity;
+        entity.facing.set_direction(self.facing);
+        if let Some(talker) = entity.talker.as_mut() {
+            talker.talks = self.talks;
+            talker.resume = self.resume;
+        }
+        if let Some(destination) = self.destination {
+            entity.go_to(destination);
+        }
+    }
+}
+
+impl SaveData {
+    pub fn to_kv(&self) -> KvFile {
+        let mut kv = KvFile::new("save");
+        kv.set("version", SAVE_VERSION);
+        kv.set("map", escape_value(&self.map_path));
+        for (i, entity) in self.entities.iter().enumerate() {
+            kv.set(&format!("entity.{i}.prefab"), escape_value(&entity.prefab.name));
+            for (key, value) in entity.prefab.overrides.iter() {
+                kv.set(&format!("entity.{i}.override.{key}"), escape_value(value));
+            }
+            if entity.player {
+                kv.set(&format!("entity.{i}.player"), true);
+            }
+            kv.set(&format!("entity.{i}.position"), format_vec2(entity.position));
+            kv.set(&format!("entity.{i}.velocity"), format_vec2(entity.velocity));
+            kv.set(&format!("entity.{i}.facing"), entity.facing.name());
+            if entity.talks > 0 {
+                kv.set(&format!("entity.{i}.talks"), entity.talks);
+            }
+            if let Some(resume) = entity.resume {
+                kv.set(&format!("entity.{i}.resume"), resume);
+            }
+            if let Some(destination) = entity.destination {
+                kv.set(&format!("entity.{i}.destination"), format_vec2(destination));
+            }
+        }
+        if let Some(dialogue) = self.dialogue {
+            kv.set("dialogue.entity", dialogue.entity);
+            kv.set("dialogue.index", dialogue.index);
+        }
+        for (key, value) in self.flags.iter() {
+            kv.set(&format!("flag.{key}"), value);
+        }
+        kv
+    }
+
+    /// Reads a save of any version up to [`SAVE_VERSION`], migrating it first if it's older
+    pub fn from_kv(mut kv: KvFile) -> GResult<Self> {
+        let version = kv.parse_value::<u32>(kv.require("version")?)?;
+        if version == 0 || version > SAVE_VERSION {
+            return Err(KvParseError::dyn_boxed(&kv.source, 0, format_args!(
+                "Save version {version} isn't supported, this build reads up to version {SAVE_VERSION}"
+            )));
+        }
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            migrate(&mut kv);
+        }
+
+        let mut save = SaveData { map_path: kv.unescaped(kv.require("map")?)?, ..Default::default() };
+
+        // Entities are numbered from 0 with no gaps
+        for i in 0.. {
+            let Some(prefab) = kv.get(&format!("entity.{i}.prefab")) else {
+                break;
+            };
+            let override_prefix = format!("entity.{i}.override.");
+            let overrides = kv.entries().iter()
+                .filter_map(|entry| Some((entry.key.strip_prefix(&override_prefix)?, entry)))
+                .map(|(key, entry)| Ok((Box::from(key), kv.unescaped(entry)?)))
+                .collect::<GResult<_>>()?;
+            save.entities.push(EntitySave {
+                prefab: PrefabSource { name: kv.unescaped(prefab)?, overrides },
+                player: kv.get_parsed::<bool>(&format!("entity.{i}.player"))?.unwrap_or(false),
+                position: kv.parse_vec2(kv.require(&format!("entity.{i}.position"))?)?,
+                velocity: kv.get_vec2(&format!("entity.{i}.velocity"))?.unwrap_or_default(),
+                facing: kv.get_parsed::<Direction>(&format!("entity.{i}.facing"))?.unwrap_or_default(),
+                talks: kv.get_parsed::<u32>(&format!("entity.{i}.talks"))?.unwrap_or(0),
+                resume: kv.get_parsed::<usize>(&format!("entity.{i}.resume"))?,
+                destination: kv.get_vec2(&format!("entity.{i}.destination"))?,
+            });
+        }
+        if save.entities.iter().filter(|e| e.player).count() != 1 {
+            return Err(KvParseError::dyn_boxed(&kv.source, 0, "Save must have exactly one entity with 'player = true'"));
+        }
+
+        if let Some(entity) = kv.get_parsed::<usize>("dialogue.entity")? {
+            if entity >= save.entities.len() {
+                return Err(KvParseError::dyn_boxed(&kv.source, 0, format_args!("'dialogue.entity' {entity} is not a saved entity")));
+            }
+            save.dialogue = Some(DialogueSave { entity, index: kv.get_parsed::<usize>("dialogue.index")?.unwrap_or(0) });
+        }
+
+        let mut flags = BTreeMap::new();
+        for entry in kv.entries() {
+            if let Some(key) = entry.key.strip_prefix("flag.") {
+                // Last one wins, same as any KvFile key
+                flags.insert(Box::from(key), kv.parse_value::<FlagValue>(entry)?);
+            }
+        }
+        save.flags = flags.into_iter().collect();
+
+        Ok(save)
+    }
+
+    pub fn to_text(&self) -> String {
+        format!("# Save file, see save.rs\n{}", self.to_kv())
+    }
+
+    pub fn from_text(source: &str, text: &str) -> GResult<Self> {
+        SaveData::from_kv(KvFile::parse(source, text)?)
+    }
+}
+
+fn format_vec2(v: Vec2) -> String {
+    format!("{}, {}", v.x, v.y)
+}
+
+/// Numbered save slots, one file each in a directory (on disk, not an asset root)
+#[derive(Clone, Debug)]
+pub struct SaveSlots {
+    dir: PathBuf,
+}
+
+impl SaveSlots {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        SaveSlots { dir: dir.into() }
+    }
+
+    pub fn path(&self, slot: usize) -> PathBuf {
+        self.dir.join(format!("slot_{slot}.sav"))
+    }
+
+    pub fn exists(&self, slot: usize) -> bool {
+        self.path(slot).is_file()
+    }
+
+    pub fn write(&self, slot: usize, save: &SaveData) -> GResult<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(slot), save.to_text())?;
+        dlog!(Level::Info, "Saved to {}", self.path(slot).display());
+        Ok(())
+    }
+
+    pub fn read(&self, slot: usize) -> GResult<SaveData> {
+        let path = self.path(slot);
+        SaveData::from_text(&path.to_string_lossy(), &fs::read_to_string(&path)?)
+    }
+
+    pub fn delete(&self, slot: usize) -> GResult<()> {
+        Ok(fs::remove_file(self.path(slot))?)
+    }
+
+    /// Slots that have a save in them, in order
+    pub fn list(&self) -> Vec<usize> {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return vec![];
+        };
+        let mut slots = dir
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name();
+                name.to_str()?.strip_prefix("slot_")?.strip_suffix(".sav")?.parse::<usize>().ok()
+            })
+            .collect::<Vec<_>>();
+        slots.sort_unstable();
+        slots
+    }
+}
+
+impl Game {
+    /// Snapshot of the game as it is right now
+    pub fn capture_save(&self) -> SaveData {
+        let mut save = SaveData { map_path: self.map_path.clone(), ..Default::default() };
+        let mut indices = HashMap::new();
+        for entity in self.em.iter() {
+            let Some(prefab) = entity.prefab.clone() else {
+                dlog!(Level::Warn, "Entity {:?} wasn't built from a prefab, leaving it out of the save", entity.id);
+                continue;
+            };
+            indices.insert(entity.id, save.entities.len());
+            save.entities.push(EntitySave::capture(entity, prefab, entity.id == self.em.player_id()));
+        }
+        save.dialogue = self.dm.progress()
+            .and_then(|(eid, index)| Some(DialogueSave { entity: *indices.get(&eid)?, index }));
+        save.flags = self.flags.iter().map(|(key, value)| (Box::from(key), value.clone())).collect();
+        save
+    }
+
+    /// Replaces the current map, entities, dialogue and flags with a save's. Nothing changes if it fails partway.
+    pub async fn restore_save(&mut self, save: &SaveData) -> GResult<()> {
+        let map = read_map_file(&self.assets, &save.map_path).await?;
+
+        let mut player = None;
+        let mut others = vec![];
+        for (i, saved) in save.entities.iter().enumerate() {
+            let overrides = saved.prefab.overrides.iter().map(|(k, v)| (&**k, &**v)).collect::<Vec<_>>();
+            let mut entity = self.eb.build_from_prefab(&self.assets, &saved.prefab.name, &overrides).await?;
+            saved.apply_to(&mut entity);
+            if saved.player {
+                player = Some((i, entity));
+            } else {
+                others.push((i, entity));
+            }
+        }
+        let Some((player_index, player)) = player else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Save has no player").into());
+        };
+
+        let mut em = EntityManager::new(player);
+        let mut ids = HashMap::from([(player_index, em.player_id())]);
+        for (i, entity) in others {
+            ids.insert(i, em.spawn(entity));
+        }
+
+        self.dm.unload_dialogue();
+        if let Some(dialogue) = save.dialogue {
+            if let Some(entity) = ids.get(&dialogue.entity).and_then(|&id| em.get(id)) {
+                self.dm.restore_progress(entity, dialogue.index, &self.loc);
+            }
+        }
+        self.em = em;
+        self.map = map;
+        self.map_path = save.map_path.clone();
+        self.collisions = collision::CollisionWorld::new();
+        self.interaction_target = None;
+        self.flags.clear();
+        for (key, value) in save.flags.iter() {
+            self.flags.set(key, value.clone());
+        }
+        // Restored dialogue takes focus back
+        self.handle_dialogue_events();
+        Ok(())
+    }
+
+    pub fn save_to_slot(&self, slot: usize) -> GResult<()> {
+        self.saves.write(slot, &self.capture_save())
+    }
+
+    pub async fn load_from_slot(&mut self, slot: usize) -> GResult<()> {
+        let save = self.saves.read(slot)?;
+        self.restore_save(&save).await?;
+        dlog!(Level::Info, "Loaded {}", self.saves.path(slot).display());
+        Ok(())
+    }
+
+    /// Quicksave/quickload on [`QUICKSAVE_KEY`]/[`QUICKLOAD_KEY`]. Failures are logged rather than ending the game
+    pub async fn handle_save_keys(&mut self) {
+        if is_key_pressed(QUICKSAVE_KEY) {
+            match self.save_to_slot(QUICKSAVE_SLOT) {
+                Ok(()) => self.show_notice(self.loc.text(UI_SAVED).to_owned()),
+                Err(e) => dlog!(Level::Error, "Quicksave failed: {}", e),
+            }
+        }
+        if is_key_pressed(QUICKLOAD_KEY) {
+            match self.load_from_slot(QUICKSAVE_SLOT).await {
+                Ok(()) => self.show_notice(self.loc.text(UI_LOADED).to_owned()),
+                Err(e) => dlog!(Level::Error, "Quickload failed: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(prefab: &str, player: bool) -> EntitySave {
+        EntitySave {
+            prefab: PrefabSource { name: Box::from(prefab), overrides: vec![] },
+            player,
+            position: vec2(640.0, 360.5),
+            velocity: vec2(-12.25, 0.0),
+            facing: Direction::UpLeft,
+            ..Default::default()
+        }
+    }
+
+    fn example() -> SaveData {
+        let npc = EntitySave {
+            prefab: PrefabSource {
+                name: Box::from("npc"),
+                overrides: vec![
+                    (Box::from("dialogue"), Box::from("dialogue/test_branching.dlg")),
+                    (Box::from("name"), Box::from("  The #1 \\ smith \n")),
+                ],
+            },
+            talks: 3,
+            resume: Some(7),
+            destination: Some(vec2(300.0, 200.5)),
+            ..entity("npc", false)
+        };
+        SaveData {
+            map_path: Box::from("maps/test_map"),
+            entities: vec![npc, entity("player", true), entity("npc", false)],
+            dialogue: Some(DialogueSave { entity: 0, index: 4 }),
+            flags: vec![
+                (Box::from("met_blacksmith"), FlagValue::Bool(true)),
+                (Box::from("quest.gold"), FlagValue::Int(-40)),
+                (Box::from("rumor"), FlagValue::from("the #1 rumor\nof two lines")),
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let save = example();
+        let text = save.to_text();
+        assert_eq!(SaveData::from_text("test", &text).unwrap(), save, "{text}");
+    }
+
+    #[test]
+    fn override_with_hash_survives() {
+        let mut save = example();
+        save.entities[2].prefab.overrides.push((Box::from("portrait"), Box::from("portraits/#2.png")));
+        let loaded = SaveData::from_text("test", &save.to_text()).unwrap();
+        assert_eq!(&*loaded.entities[2].prefab.overrides[0].1, "portraits/#2.png");
+    }
+
+    #[test]
+    fn version_1_is_migrated() {
+        let text = "\
+            version = 1\n\
+            map = maps/test_map\n\
+            entity.0.prefab = player\n\
+            entity.0.player = true\n\
+            entity.0.position = 10, 20\n\
+            entity.1.prefab = npc\n\
+            entity.1.override.dialogue = dialogue/test_dialogue.txt\n\
+            entity.1.position = 30, 40\n\
+            entity.1.facing = left\n\
+            dialogue.entity = 1\n\
+            dialogue.index = 2\n";
+        let save = SaveData::from_text("test", text).unwrap();
+        assert_eq!(&*save.map_path, "maps/test_map");
+        assert_eq!(save.entities.len(), 2);
+        assert!(save.entities[0].player && !save.entities[1].player);
+        assert_eq!(save.entities[1].position, vec2(30.0, 40.0));
+        assert_eq!(save.entities[1].facing, Direction::Left);
+        assert_eq!(save.entities[1].prefab.overrides, vec![(Box::from("dialogue"), Box::from("dialogue/test_dialogue.txt"))]);
+        assert_eq!((save.entities[1].talks, save.entities[1].resume), (0, None));
+        assert_eq!(save.dialogue, Some(DialogueSave { entity: 1, index: 2 }));
+        assert!(save.flags.is_empty());
+    }
+
+    #[test]
+    fn version_4_backslashes_are_kept() {
+        let text = "version = 4\nmap = maps\\test_map\nentity.0.prefab = player\nentity.0.player = true\nentity.0.position = 0, 0\n";
+        assert_eq!(&*SaveData::from_text("test", text).unwrap().map_path, "maps\\test_map");
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        for version in [0, SAVE_VERSION + 1] {
+            let text = format!("version = {version}\nmap = maps/test_map\nentity.0.prefab = player\nentity.0.player = true\nentity.0.position = 0, 0\n");
+            let e = SaveData::from_text("test", &text).unwrap_err().to_string();
+            assert!(e.contains(&format!("Save version {version} isn't supported")), "{e}");
+        }
+    }
+
+    #[test]
+    fn needs_exactly_one_player() {
+        let mut save = example();
+        save.entities[1].player = false;
+        assert!(SaveData::from_text("test", &save.to_text()).is_err());
+        save.entities[0].player = true;
+        save.entities[1].player = true;
+        assert!(SaveData::from_text("test", &save.to_text()).is_err());
+    }
+
+    #[test]
+    fn dialogue_entity_must_exist() {
+        let mut save = example();
+        save.dialogue = Some(DialogueSave { entity: 3, index: 0 });
+        assert!(SaveData::from_text("test", &save.to_text()).is_err());
+    }
+
+    #[test]
+    fn bad_destinations_are_rejected() {
+        for destination in ["", "1, 2, 3", "1, x"] {
+            let text = format!("version = 6\nmap = m\nentity.0.prefab = p\nentity.0.player = true\nentity.0.position = 0, 0\nentity.0.destination = {destination}\n");
+            assert!(SaveData::from_text("test", &text).unwrap_err().to_string().contains("for 'entity.0.destination'"), "{destination}");
+        }
+    }
+
+    #[test]
+    fn moved_entities_stay_moved() {
+        let mut em = EntityManager::new(Entity::bare(Rect::new(0.0, 0.0, 10.0, 10.0)));
+        let npc = em.spawn(Entity::bare(Rect::new(50.0, 0.0, 10.0, 10.0)).with_behavior(Behavior::wander(150.0)));
+        let mut flags = Flags::new();
+        GameDialogueHost { flags: &mut flags, em: &mut em }.run_effect(npc, &DialogueEffect::Move(vec2(400.0, 200.0)));
+
+        let saved = EntitySave::capture(em.get(npc).unwrap(), PrefabSource::default(), false);
+        assert_eq!(saved.destination, Some(vec2(400.0, 200.0)));
+        let loaded = SaveData::from_text("test", &SaveData { entities: vec![saved, entity("player", true)], ..example() }.to_text()).unwrap();
+
+        // As rebuilt from its prefab, wandering again until the save's applied
+        let mut rebuilt = Entity::bare(Rect::new(50.0, 0.0, 10.0, 10.0)).with_behavior(Behavior::wander(150.0));
+        loaded.entities[0].apply_to(&mut rebuilt);
+        let behavior = rebuilt.behavior.expect("should be walking there again");
+        assert!(matches!(behavior.kind, BehaviorKind::GoTo { destination } if destination == vec2(400.0, 200.0)));
+        assert!(behavior.face_player_when_talking);
+    }
+
+    #[test]
+    fn prefab_behaviors_are_left_to_the_prefab() {
+        let patrolling = Entity::bare(Rect::new(0.0, 0.0, 10.0, 10.0)).with_behavior(Behavior::patrol(vec![vec2(0.0, 0.0), vec2(10.0, 0.0)]));
+        assert_eq!(EntitySave::capture(&patrolling, PrefabSource::default(), false).destination, None);
+    }
+
+    #[test]
+    fn migrations_cover_every_version() {
+        assert_eq!(MIGRATIONS.len(), SAVE_VERSION as usize - 1);
+    }
+}
@@ -0,0 +1,420 @@
+//! Collision shapes beyond axis-aligned rects.
+//!
+//! Every shape is treated as a *core* (a point, a segment or a convex polygon) grown by a radius:
+//! - [`Shape::Rect`] / [`Shape::Obb`] -> polygon, radius 0
+//! - [`Shape::Circle`] -> point + radius
+//! - [`Shape::Capsule`] -> segment + radius
+//!
+//! which lets every pair of shapes share the same handful of queries instead of a function per pair.
+use crate::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    /// Axis-aligned, same as a [`RectBounded`] boundary
+    Rect(Rect),
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    /// Oriented rect, rotated `rotation` radians (clockwise on screen) about its center
+    Obb {
+        center: Vec2,
+        half_extents: Vec2,
+        rotation: f32,
+    },
+    /// Every point within `radius` of the segment `a`-`b`
+    Capsule {
+        a: Vec2,
+        b: Vec2,
+        radius: f32,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Core {
+    Point(Vec2),
+    Segment(Vec2, Vec2),
+    Polygon([Vec2; 4]),
+}
+
+impl Shape {
+    pub fn as_rect(&self) -> Option<Rect> {
+        match *self {
+            Shape::Rect(rect) => Some(rect),
+            _ => None,
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        match *self {
+            Shape::Rect(rect) => rect.center(),
+            Shape::Circle { center, .. } | Shape::Obb { center, .. } => center,
+            Shape::Capsule { a, b, .. } => (a + b) / 2.0,
+        }
+    }
+
+    /// Smallest axis-aligned rect containing the shape
+    pub fn aabb(&self) -> Rect {
+        let (min, max) = match self.core() {
+            Core::Point(p) => (p, p),
+            Core::Segment(a, b) => (a.min(b), a.max(b)),
+            Core::Polygon(points) => points.iter().fold((points[0], points[0]), |(min, max), &p| (min.min(p), max.max(p))),
+        };
+        let r = Vec2::splat(self.radius());
+        Rect::new(min.x - r.x, min.y - r.y, max.x - min.x + 2.0 * r.x, max.y - min.y + 2.0 * r.y)
+    }
+
+    /// Same shape grown outward by `amount` on every side (rects keep their corners sharp)
+    pub fn inflated(&self, amount: f32) -> Shape {
+        match *self {
+            Shape::Rect(rect) => Shape::Rect(Rect::new(rect.x - amount, rect.y - amount, rect.w + 2.0 * amount, rect.h + 2.0 * amount)),
+            Shape::Circle { center, radius } => Shape::Circle { center, radius: radius + amount },
+            Shape::Obb { center, half_extents, rotation } => Shape::Obb { center, half_extents: half_extents + amount, rotation },
+            Shape::Capsule { a, b, radius } => Shape::Capsule { a, b, radius: radius + amount },
+        }
+    }
+
+    fn radius(&self) -> f32 {
+        match *self {
+            Shape::Circle { radius, .. } | Shape::Capsule { radius, .. } => radius,
+            Shape::Rect(_) | Shape::Obb { .. } => 0.0,
+        }
+    }
+
+    fn core(&self) -> Core {
+        match *self {
+            Shape::Rect(rect) => Core::Polygon([
+                rect.point(),
+                vec2(rect.right(), rect.top()),
+                vec2(rect.right(), rect.bottom()),
+                vec2(rect.left(), rect.bottom()),
+            ]),
+            Shape::Circle { center, .. } => Core::Point(center),
+            Shape::Obb { center, half_extents: h, rotation } => {
+                let rot = Vec2::from_angle(rotation);
+                Core::Polygon([vec2(-h.x, -h.y), vec2(h.x, -h.y), vec2(h.x, h.y), vec2(-h.x, h.y)].map(|corner| center + rot.rotate(corner)))
+            }
+            Shape::Capsule { a, b, .. } => Core::Segment(a, b),
+        }
+    }
+
+    /// Signed distance from a point to the shape's surface, negative inside
+    pub fn signed_distance(&self, p: Vec2) -> f32 {
+        let core_distance = match self.core() {
+            Core::Point(c) => p.distance(c),
+            Core::Segment(a, b) => p.distance(closest_on_segment(p, a, b)),
+            Core::Polygon(points) => {
+                let edge_distance = edges(&points).map(|(a, b)| p.distance(closest_on_segment(p, a, b))).fold(f32::INFINITY, f32::min);
+                if polygon_contains(&points, p) { -edge_distance } else { edge_distance }
+            }
+        };
+        core_distance - self.radius()
+    }
+
+    pub fn contains_point(&self, p: Vec2) -> bool {
+        self.signed_distance(p) <= 0.0
+    }
+
+    /// Whether `other` lies entirely within this shape
+    pub fn contains(&self, other: &Shape) -> bool {
+        // Every shape here is convex, so it's enough for other's core points (grown by its radius) to fit
+        let r = other.radius();
+        match other.core() {
+            Core::Point(p) => self.signed_distance(p) <= -r,
+            Core::Segment(a, b) => self.signed_distance(a) <= -r && self.signed_distance(b) <= -r,
+            Core::Polygon(points) => points.iter().all(|&p| self.signed_distance(p) <= -r),
+        }
+    }
+
+    /// Whether the shapes overlap (touching doesn't count)
+    pub fn intersects(&self, other: &Shape) -> bool {
+        self.penetration(other).is_some()
+    }
+
+    /// Minimum translation that moves this shape out of `other`, None if they don't overlap
+    pub fn penetration(&self, other: &Shape) -> Option<Vec2> {
+        let (core_a, core_b) = (self.core(), other.core());
+        let (ra, rb) = (self.radius(), other.radius());
+
+        if let (Core::Polygon(_), Core::Polygon(_)) = (core_a, core_b) {
+            return sat(core_a, ra, core_b, rb, self.center() - other.center());
+        }
+
+        match closest_points(core_a, core_b) {
+            // Cores apart, so the shapes only overlap where the radii reach across the gap
+            Some((pa, pb)) => {
+                let distance = pa.distance(pb);
+                if distance >= ra + rb || distance == 0.0 {
+                    return None;
+                }
+                Some((pa - pb) / distance * (ra + rb - distance))
+            }
+            // Cores overlap themselves
+            None => sat(core_a, ra, core_b, rb, self.center() - other.center()),
+        }
+    }
+
+    /// Debug outline
+    pub fn draw_lines(&self, thickness: f32, color: Color) {
+        match *self {
+            Shape::Rect(Rect { x, y, w, h }) => draw_rectangle_lines(x, y, w, h, thickness, color),
+            Shape::Circle { center, radius } => draw_circle_lines(center.x, center.y, radius, thickness, color),
+            Shape::Obb { .. } => {
+                if let Core::Polygon(points) = self.core() {
+                    for (a, b) in edges(&points) {
+                        draw_line(a.x, a.y, b.x, b.y, thickness, color);
+                    }
+                }
+            }
+            Shape::Capsule { a, b, radius } => {
+                draw_circle_lines(a.x, a.y, radius, thickness, color);
+                draw_circle_lines(b.x, b.y, radius, thickness, color);
+                let side = (b - a).perp().normalize_or_zero() * radius;
+                draw_line(a.x + side.x, a.y + side.y, b.x + side.x, b.y + side.y, thickness, color);
+                draw_line(a.x - side.x, a.y - side.y, b.x - side.x, b.y - side.y, thickness, color);
+            }
+        }
+    }
+}
+
+fn edges(points: &[Vec2; 4]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    (0..4).map(|i| (points[i], points[(i + 1) % 4]))
+}
+
+fn closest_on_segment(p: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    let len_sq = ab.length_squared();
+    if len_sq == 0.0 {
+        return a;
+    }
+    a + ab * ((p - a).dot(ab) / len_sq).clamp(0.0, 1.0)
+}
+
+/// Closest points between two segments (Real-Time Collision Detection, 5.1.9)
+fn closest_between_segments(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> (Vec2, Vec2) {
+    let (d1, d2, r) = (q1 - p1, q2 - p2, p1 - p2);
+    let (a, e, f) = (d1.length_squared(), d2.length_squared(), d2.dot(r));
+
+    let (s, t) = if a == 0.0 && e == 0.0 {
+        (0.0, 0.0)
+    } else if a == 0.0 {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(r);
+        if e == 0.0 {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            let mut s = if denom != 0.0 { ((b * f - c * e) / denom).clamp(0.0, 1.0) } else { 0.0 };
+            let mut t = (b * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+fn polygon_contains(points: &[Vec2; 4], p: Vec2) -> bool {
+    // Convex, so inside means on the same side of every edge
+    let sides = edges(points).map(|(a, b)| (b - a).perp_dot(p - a)).collect::<Vec<_>>();
+    sides.iter().all(|&side| side >= 0.0) || sides.iter().all(|&side| side <= 0.0)
+}
+
+fn segments_cross(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> bool {
+    let (a, b) = closest_between_segments(p1, q1, p2, q2);
+    a.distance_squared(b) == 0.0
+}
+
+/// Closest points (on a, on b) between two cores, None if the cores overlap
+fn closest_points(a: Core, b: Core) -> Option<(Vec2, Vec2)> {
+    match (a, b) {
+        (Core::Point(p), Core::Point(q)) => (p != q).then_some((p, q)),
+        (Core::Point(p), Core::Segment(q1, q2)) => {
+            let q = closest_on_segment(p, q1, q2);
+            (p != q).then_some((p, q))
+        }
+        (Core::Segment(..), Core::Point(_)) | (Core::Polygon(_), Core::Point(_) | Core::Segment(..)) => {
+            closest_points(b, a).map(|(pb, pa)| (pa, pb))
+        }
+        (Core::Segment(p1, q1), Core::Segment(p2, q2)) => {
+            let (pa, pb) = closest_between_segments(p1, q1, p2, q2);
+            (pa != pb).then_some((pa, pb))
+        }
+        (Core::Point(p), Core::Polygon(points)) => {
+            if polygon_contains(&points, p) {
+                return None;
+            }
+            edges(&points)
+                .map(|(e1, e2)| (p, closest_on_segment(p, e1, e2)))
+                .min_by(|(a1, b1), (a2, b2)| a1.distance_squared(*b1).total_cmp(&a2.distance_squared(*b2)))
+        }
+        (Core::Segment(s1, s2), Core::Polygon(points)) => {
+            if polygon_contains(&points, s1) || polygon_contains(&points, s2) || edges(&points).any(|(e1, e2)| segments_cross(s1, s2, e1, e2)) {
+                return None;
+            }
+            edges(&points)
+                .map(|(e1, e2)| closest_between_segments(s1, s2, e1, e2))
+                .min_by(|(a1, b1), (a2, b2)| a1.distance_squared(*b1).total_cmp(&a2.distance_squared(*b2)))
+        }
+        // Handled by SAT in Shape::penetration
+        (Core::Polygon(_), Core::Polygon(_)) => None,
+    }
+}
+
+fn project(core: Core, radius: f32, axis: Vec2) -> (f32, f32) {
+    let (min, max) = match core {
+        Core::Point(p) => (p.dot(axis), p.dot(axis)),
+        Core::Segment(a, b) => (a.dot(axis).min(b.dot(axis)), a.dot(axis).max(b.dot(axis))),
+        Core::Polygon(points) => points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| (min.min(p.dot(axis)), max.max(p.dot(axis)))),
+    };
+    (min - radius, max + radius)
+}
+
+fn axes(core: Core) -> Vec<Vec2> {
+    match core {
+        Core::Point(_) => vec![],
+        Core::Segment(a, b) => {
+            let d = (b - a).normalize_or_zero();
+            if d == Vec2::ZERO { vec![] } else { vec![d, d.perp()] }
+        }
+        // Opposite edges of our quads are parallel, so two axes cover all four
+        Core::Polygon(points) => [(points[0], points[1]), (points[1], points[2])]
+            .into_iter()
+            .filter_map(|(a, b)| (b - a).perp().try_normalize())
+            .collect(),
+    }
+}
+
+/// Separating axis test, giving the minimum translation for `a` out of `b`.
+/// `a_from_b` (center to center) breaks ties, and stands in as an axis when neither core has any (two points).
+fn sat(a: Core, ra: f32, b: Core, rb: f32, a_from_b: Vec2) -> Option<Vec2> {
+    let mut candidates = axes(a);
+    candidates.extend(axes(b));
+    if candidates.is_empty() {
+        candidates.push(a_from_b.try_normalize().unwrap_or(Vec2::X));
+    }
+
+    let mut best: Option<(f32, Vec2)> = None;
+    for axis in candidates {
+        let (min_a, max_a) = project(a, ra, axis);
+        let (min_b, max_b) = project(b, rb, axis);
+        // How far a has to go either way along the axis to clear b, which differ when one contains the other
+        let (forward, backward) = (max_b - min_a, max_a - min_b);
+        if forward <= 0.0 || backward <= 0.0 {
+            return None;
+        }
+        let prefer_forward = forward < backward || (forward == backward && a_from_b.dot(axis) >= 0.0);
+        let (depth, direction) = if prefer_forward { (forward, axis) } else { (backward, -axis) };
+        if best.is_none_or(|(best_depth, _)| depth < best_depth) {
+            best = Some((depth, direction));
+        }
+    }
+    best.map(|(depth, direction)| direction * depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_4;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-4, "{a} != {b}");
+    }
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Shape {
+        Shape::Rect(Rect::new(x, y, w, h))
+    }
+
+    fn circle(x: f32, y: f32, radius: f32) -> Shape {
+        Shape::Circle { center: vec2(x, y), radius }
+    }
+
+    #[test]
+    fn rect_penetration_takes_the_shallow_axis() {
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+        assert_near(a.penetration(&rect(8.0, 2.0, 10.0, 10.0)).unwrap(), vec2(-2.0, 0.0));
+        assert_near(a.penetration(&rect(1.0, -9.0, 10.0, 10.0)).unwrap(), vec2(0.0, 1.0));
+        // Touching isn't overlapping
+        assert!(a.penetration(&rect(10.0, 0.0, 10.0, 10.0)).is_none());
+        assert!(!a.intersects(&rect(20.0, 0.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn round_shapes() {
+        assert_near(circle(0.0, 0.0, 5.0).penetration(&circle(8.0, 0.0, 5.0)).unwrap(), vec2(-2.0, 0.0));
+        assert!(circle(0.0, 0.0, 5.0).penetration(&circle(10.0, 0.0, 5.0)).is_none());
+
+        let wall = rect(0.0, 0.0, 10.0, 10.0);
+        assert_near(circle(12.0, 5.0, 3.0).penetration(&wall).unwrap(), vec2(1.0, 0.0));
+        // Center inside the rect has to use SAT, still the shortest way out
+        assert_near(circle(9.0, 5.0, 1.0).penetration(&wall).unwrap(), vec2(2.0, 0.0));
+        // Beyond the corner the radius doesn't reach
+        assert!(circle(13.0, 13.0, 4.0).penetration(&wall).is_none());
+
+        let capsule = Shape::Capsule { a: vec2(0.0, 0.0), b: vec2(10.0, 0.0), radius: 2.0 };
+        assert_near(capsule.penetration(&circle(5.0, 3.0, 2.0)).unwrap(), vec2(0.0, -1.0));
+        assert!(capsule.penetration(&circle(14.5, 0.0, 2.0)).is_none());
+    }
+
+    #[test]
+    fn rotated_rect_sat() {
+        // A diamond reaching ~7.07 either way along x
+        let diamond = Shape::Obb { center: Vec2::ZERO, half_extents: vec2(5.0, 5.0), rotation: FRAC_PI_4 };
+        let reach = 5.0 * std::f32::consts::SQRT_2;
+        assert_near(diamond.penetration(&rect(6.0, -1.0, 10.0, 2.0)).unwrap(), vec2(6.0 - reach, 0.0));
+        // Inside the rotated rect's aabb but clear of its edges
+        assert!(diamond.penetration(&rect(5.0, 5.0, 2.0, 2.0)).is_none());
+        let aabb = diamond.aabb();
+        assert_near(vec2(aabb.w, aabb.h), Vec2::splat(2.0 * reach));
+    }
+
+    #[test]
+    fn penetration_is_antisymmetric() {
+        let shapes = [
+            rect(0.0, 0.0, 10.0, 10.0),
+            circle(9.0, 4.0, 3.0),
+            Shape::Obb { center: vec2(4.0, 12.0), half_extents: vec2(6.0, 3.0), rotation: 0.3 },
+            Shape::Capsule { a: vec2(-3.0, 5.0), b: vec2(3.0, 9.0), radius: 2.0 },
+        ];
+        for a in shapes.iter() {
+            for b in shapes.iter().filter(|b| *b != a) {
+                match (a.penetration(b), b.penetration(a)) {
+                    (Some(ab), Some(ba)) => assert_near(ab, -ba),
+                    (ab, ba) => assert_eq!(ab.is_some(), ba.is_some(), "{a:?} vs {b:?}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pushed_out_means_not_overlapping() {
+        let wall = Shape::Obb { center: vec2(0.0, 0.0), half_extents: vec2(20.0, 4.0), rotation: 0.5 };
+        for mover in [circle(3.0, 2.0, 5.0), rect(-4.0, -2.0, 6.0, 6.0), Shape::Capsule { a: vec2(-5.0, 3.0), b: vec2(5.0, 3.0), radius: 1.0 }] {
+            let push = mover.penetration(&wall).unwrap();
+            let moved = match mover {
+                Shape::Circle { center, radius } => Shape::Circle { center: center + push * 1.001, radius },
+                Shape::Rect(r) => Shape::Rect(r.offset(push * 1.001)),
+                Shape::Capsule { a, b, radius } => Shape::Capsule { a: a + push * 1.001, b: b + push * 1.001, radius },
+                Shape::Obb { .. } => unreachable!(),
+            };
+            assert!(!moved.intersects(&wall), "{mover:?} pushed by {push}");
+        }
+    }
+
+    #[test]
+    fn distance_and_containment() {
+        let r = rect(0.0, 0.0, 10.0, 10.0);
+        assert!((r.signed_distance(vec2(15.0, 5.0)) - 5.0).abs() < 1e-5);
+        assert!((r.signed_distance(vec2(8.0, 5.0)) + 2.0).abs() < 1e-5);
+        assert!(r.contains(&circle(5.0, 5.0, 4.0)));
+        assert!(!r.contains(&circle(5.0, 5.0, 6.0)));
+        assert!(circle(0.0, 0.0, 2.0).contains_point(vec2(1.0, 1.0)));
+    }
+}
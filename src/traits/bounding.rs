@@ -32,4 +32,100 @@ pub trait RectBounded {
     fn bsize(&self) -> Vec2 {
         self.ref_boundary().size()
     }
+
+    /// Swept AABB test: if moving by `delta` would run into `other`, when (as a fraction of `delta`) and which side.
+    ///
+    /// Only reports contacts being moved *into*, so sliding along or moving away from something we're
+    /// touching is not a hit. Already overlapping (beyond float error) is not a hit either, see [`crate::collision::depenetrate`].
+    fn sweep(&self, delta: Vec2, other: &impl RectBounded) -> Option<SweepHit> {
+        let this = self.ref_boundary();
+        let other = other.ref_boundary();
+
+        // Minkowski difference, i.e. sweep our top left corner as a point against other grown by our size
+        let lo = vec2(other.left() - this.w, other.top() - this.h);
+        let hi = vec2(other.right(), other.bottom());
+        let p = this.point();
+
+        let (mut t_entry, mut t_exit, mut normal) = (f32::NEG_INFINITY, f32::INFINITY, Vec2::ZERO);
+        // Speed along the axis we'd enter on, to turn a negative entry time back into a penetration depth
+        let mut entry_speed = 0.0;
+        for (axis, unit) in [(0, Vec2::X), (1, Vec2::Y)] {
+            let (p, d, lo, hi) = (p[axis], delta[axis], lo[axis], hi[axis]);
+            if d == 0.0 {
+                // Not moving on this axis, so we must already be (strictly) within it to ever hit
+                if p <= lo || p >= hi {
+                    return None;
+                }
+                continue;
+            }
+            let (t_lo, t_hi) = ((lo - p) / d, (hi - p) / d);
+            let (near, far) = if d > 0.0 { (t_lo, t_hi) } else { (t_hi, t_lo) };
+            if near > t_entry {
+                t_entry = near;
+                normal = -unit * d.signum();
+                entry_speed = d.abs();
+            }
+            t_exit = t_exit.min(far);
+        }
+
+        if t_entry > t_exit || t_exit <= 0.0 || t_entry > 1.0 {
+            return None;
+        }
+        if t_entry < 0.0 && -t_entry * entry_speed > SWEEP_EPSILON {
+            return None;
+        }
+        Some(SweepHit { time: t_entry.max(0.0), normal })
+    }
+}
+
+/// Exact collision shape, for anything whose [`RectBounded`] boundary is only a stand-in (see [`ColliderShape`])
+pub trait ShapeBounded {
+    fn shape(&self) -> Shape;
+
+    fn intersects_shape(&self, other: &impl ShapeBounded) -> bool {
+        self.shape().intersects(&other.shape())
+    }
+
+    /// Whether `other` lies entirely within us
+    fn contains_shape(&self, other: &impl ShapeBounded) -> bool {
+        self.shape().contains(&other.shape())
+    }
+
+    /// Shortest offset that would move us out of `other`, None if not overlapping
+    fn penetration(&self, other: &impl ShapeBounded) -> Option<Vec2> {
+        self.shape().penetration(&other.shape())
+    }
+}
+
+/// Float error (in logical pixels) tolerated by [`RectBounded::sweep`] when deciding whether we're touching or actually overlapping
+pub const SWEEP_EPSILON: f32 = 1e-3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SweepHit {
+    /// Fraction of the sweep's delta travelled before contact, in [0, 1]
+    pub time: f32,
+    /// Unit normal of the surface hit, pointing back toward the mover
+    pub normal: Vec2,
+}
+
+impl RectBounded for Rect {
+    fn ref_boundary(&self) -> &Rect {
+        self
+    }
+
+    fn mut_boundary(&mut self) -> &mut Rect {
+        self
+    }
+}
+
+impl ShapeBounded for Rect {
+    fn shape(&self) -> Shape {
+        Shape::Rect(*self)
+    }
+}
+
+impl ShapeBounded for Shape {
+    fn shape(&self) -> Shape {
+        *self
+    }
 }
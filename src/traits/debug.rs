@@ -4,6 +4,8 @@ use crate::prelude::*;
 /// Ok value comes with the current value
 /// that was toggled to.
 pub trait Debuggable {
+    // There's nothing to say about being unimplemented past the Err itself
+    #[allow(clippy::result_unit_err)]
     fn toggle_hitbox(&mut self) -> Result<bool, ()> {
         Err(())
     }
@@ -1,8 +1,11 @@
 pub trait Updateable {
+    /// Accelerates/decelerates for this frame, before any movement
+    fn update_velocity(&mut self, _dt: f32) {}
     fn update_x(&mut self, dt: f32);
     fn update_y(&mut self, dt: f32);
 
     fn update(&mut self, dt: f32) {
+        self.update_velocity(dt);
         self.update_x(dt);
         self.update_y(dt);
     }
@@ -1,22 +1,199 @@
 use crate::prelude::*;
+use std::ops::Range;
 
-/// Should be drawn with ui camera ([`set_ui_camera`]) for expected behavior.
-pub fn draw_dialogue_frame(pset: &PSet) {
+/// Where the dialogue box goes, across the bottom fifth of the logical window
+pub fn dialogue_frame_rect(pset: &PSet) -> Rect {
     let PxWindow { width: logical_w, height: logical_h } = pset.logical.window;
     let d_frame_height = logical_h / 5.0;
-    draw_rectangle(0.0, logical_h - d_frame_height, logical_w, d_frame_height, WHITE);
-    draw_rectangle_lines(0.0, logical_h - d_frame_height, logical_w, d_frame_height, 3.0, GRAY);
+    Rect::new(0.0, logical_h - d_frame_height, logical_w, d_frame_height)
 }
 
 /// Should be drawn with ui camera ([`set_ui_camera`]) for expected behavior.
-pub fn draw_dialogue_text(pset: &PSet, text: &str, maybe_params: Option<TextParams>) {
-    let logical_h = pset.logical.window.height;
-    if let Some(params) = maybe_params {
-        dlog!(Level::Info, draw_text_ex(text, 0.0, logical_h - logical_h / 5.0 + params.font_size as f32, params));
-    } else {
-        // use default ttf font -- we will probably never have a None maybe_params but this is here b/c easy to implement
-        draw_text(text, 0.0, logical_h - logical_h / 5.0 + DEFAULT_FONT_SIZE as f32, DEFAULT_FONT_SIZE as f32, DEFAULT_FONT_COLOR);
+pub fn draw_dialogue_frame(pset: &PSet) {
+    let frame = dialogue_frame_rect(pset);
+    draw_rectangle(frame.x, frame.y, frame.w, frame.h, WHITE);
+    draw_rectangle_lines(frame.x, frame.y, frame.w, frame.h, 3.0, GRAY);
+}
+
+fn dialogue_padding(frame: Rect) -> f32 {
+    frame.h / 10.0
+}
+
+/// Square inside the left end of the dialogue frame where a portrait goes
+pub fn dialogue_portrait_rect(pset: &PSet) -> Rect {
+    let frame = dialogue_frame_rect(pset);
+    let padding = dialogue_padding(frame);
+    let side = frame.h - padding * 2.0;
+    Rect::new(frame.x + padding, frame.y + padding, side, side)
+}
+
+/// Where dialogue text goes inside the frame, past the portrait if there is one
+pub fn dialogue_text_rect(pset: &PSet, with_portrait: bool) -> Rect {
+    let frame = dialogue_frame_rect(pset);
+    let padding = dialogue_padding(frame);
+    let left = if with_portrait { dialogue_portrait_rect(pset).right() + padding } else { frame.x + padding };
+    Rect::new(left, frame.y + padding, frame.right() - padding - left, frame.h - padding * 2.0)
+}
+
+/// Distance between the baselines of rows of dialogue text
+pub fn dialogue_line_height(font_size: u16) -> f32 {
+    font_size as f32 * DIALOGUE_LINE_SPACING
+}
+
+/// Draws a portrait scaled to fit [`dialogue_portrait_rect`].
+/// Should be drawn with ui camera ([`set_ui_camera`]) for expected behavior.
+pub fn draw_dialogue_portrait(pset: &PSet, portrait: &Texture2D) {
+    let area = dialogue_portrait_rect(pset);
+    let texture_size = portrait.size();
+    let scale = (area.w / texture_size.x).min(area.h / texture_size.y);
+    let size = texture_size * scale;
+    // Centered in the square so text lines up the same whatever the portrait's aspect
+    let tl = area.point() + (area.size() - size) / 2.0;
+    draw_texture_ex(portrait, tl.x, tl.y, WHITE, DrawTextureParams { dest_size: Some(size), ..Default::default() });
+}
+
+/// Speaker's name in a small box sitting on the left end of the dialogue frame.
+/// Should be drawn with ui camera ([`set_ui_camera`]) for expected behavior.
+pub fn draw_dialogue_name_plate(pset: &PSet, name: &str, maybe_params: Option<TextParams>) {
+    if name.is_empty() {
+        return;
     }
+    let frame = dialogue_frame_rect(pset);
+    let mut params = maybe_params.unwrap_or(TextParams {
+        font_size: DEFAULT_FONT_SIZE,
+        color: DEFAULT_FONT_COLOR,
+        ..Default::default()
+    });
+    params.font_size = (params.font_size as f32 * 0.7) as u16;
+    let padding = params.font_size as f32 / 3.0;
+    let dims = measure_text(name, params.font, params.font_size, 1.0);
+
+    let plate_w = dims.width + padding * 2.0;
+    let plate_h = params.font_size as f32 + padding * 2.0;
+    let plate_y = frame.y - plate_h;
+    draw_rectangle(frame.x, plate_y, plate_w, plate_h, WHITE);
+    draw_rectangle_lines(frame.x, plate_y, plate_w, plate_h, 3.0, GRAY);
+    draw_text_ex(name, frame.x + padding, plate_y + padding + dims.offset_y, params);
+}
+
+/// Width of `text[range]` styled by `spans` (see [`styled_pieces`]) on top of `base`
+pub fn measure_styled_text(text: &str, spans: &[StyledSpan], range: Range<usize>, base: &TextParams, italic_font: Option<&Font>) -> f32 {
+    styled_pieces(spans, range)
+        .map(|(piece, style)| {
+            let params = style.apply(base, italic_font);
+            measure_text(&text[piece], params.font, params.font_size, 1.0).width
+        })
+        .sum()
+}
+
+/// Offset of an animated character (see [`TextStyle::is_animated`]) this frame. `at` is its byte offset into the line
+fn text_effect_offset(style: TextStyle, at: usize, font_size: u16) -> Vec2 {
+    let mut offset = Vec2::ZERO;
+    if style.wave {
+        offset.y += (get_time() as f32 * 6.0 - at as f32 * 0.5).sin() * font_size as f32 * 0.08;
+    }
+    if style.shake {
+        let amount = font_size as f32 * 0.04;
+        offset += vec2(rand::gen_range(-amount, amount), rand::gen_range(-amount, amount));
+    }
+    offset
+}
+
+/// Draws the page of dialogue text showing in [`dialogue_text_rect`], one row per range in [`DialogueView::rows`]
+/// (see [`wrap_rows`]), styled by its markup and cut off where it's revealed up to.
+/// Should be drawn with ui camera ([`set_ui_camera`]) for expected behavior.
+pub fn draw_dialogue_text(pset: &PSet, view: &DialogueView) {
+    let area = dialogue_text_rect(pset, view.portrait.is_some());
+    // use default ttf font -- we will probably never have a None text_params but this is here b/c easy to implement
+    let base = view.text_params.clone().unwrap_or(TextParams {
+        font_size: DEFAULT_FONT_SIZE,
+        color: DEFAULT_FONT_COLOR,
+        ..Default::default()
+    });
+    let line_height = dialogue_line_height(base.font_size);
+
+    // Rows are as tall as the biggest text in them, see row_height
+    let mut top = area.y;
+    for row in view.rows.iter() {
+        let end = row.end.min(view.revealed);
+        if end <= row.start {
+            break;
+        }
+        let height = row_height(view.spans, row.clone(), line_height);
+        let baseline = top + base.font_size as f32 * height / line_height;
+        top += height;
+        let mut x = area.x;
+        for (piece, style) in styled_pieces(view.spans, row.start..end) {
+            let params = style.apply(&base, view.italic_font);
+            if !style.is_animated() {
+                x += draw_text_ex(&view.text[piece], x, baseline, params).width;
+                continue;
+            }
+            for (at, c) in view.text[piece.clone()].char_indices() {
+                let at = piece.start + at;
+                let offset = text_effect_offset(style, at, params.font_size);
+                x += draw_text_ex(&view.text[at..at + c.len_utf8()], x + offset.x, baseline + offset.y, params.clone()).width;
+            }
+        }
+    }
+}
+
+/// Choices box, stacked above the right end of the dialogue frame with a marker on the `selected` one.
+/// Should be drawn with ui camera ([`set_ui_camera`]) for expected behavior.
+pub fn draw_dialogue_choices(pset: &PSet, choices: &[&DialogueChoice], selected: usize, maybe_params: Option<TextParams>) {
+    if choices.is_empty() {
+        return;
+    }
+    let frame = dialogue_frame_rect(pset);
+    let params = maybe_params.unwrap_or(TextParams {
+        font_size: DEFAULT_FONT_SIZE,
+        color: DEFAULT_FONT_COLOR,
+        ..Default::default()
+    });
+    let line_height = dialogue_line_height(params.font_size);
+    let padding = params.font_size as f32 / 4.0;
+
+    let box_w = frame.w / 3.0;
+    // Wrapped past the marker, so rows after a choice's first line up under its text
+    let marker_w = measure_text("> ", params.font, params.font_size, 1.0).width;
+    let texts = choices.iter().map(|choice| &*choice.text).collect::<Vec<_>>();
+    let wrapped = wrap_choices(&texts, box_w - padding * 2.0 - marker_w, |text| measure_text(text, params.font, params.font_size, 1.0).width);
+    let row_count = wrapped.iter().map(Vec::len).sum::<usize>();
+
+    let box_h = row_count as f32 * line_height + padding * 2.0;
+    let box_x = frame.right() - box_w;
+    let box_y = frame.y - box_h;
+    draw_rectangle(box_x, box_y, box_w, box_h, WHITE);
+    draw_rectangle_lines(box_x, box_y, box_w, box_h, 3.0, GRAY);
+
+    let rows = wrapped.iter().enumerate().flat_map(|(i, rows)| rows.iter().enumerate().map(move |(j, row)| (i, j, row)));
+    for (n, (i, j, row)) in rows.enumerate() {
+        let baseline = box_y + padding + (n + 1) as f32 * line_height - (line_height - params.font_size as f32);
+        if i == selected && j == 0 {
+            draw_text_ex("> ", box_x + padding, baseline, params.clone());
+        }
+        draw_text_ex(row, box_x + padding + marker_w, baseline, params.clone());
+    }
+}
+
+/// Short message in a box in the top left corner, see [`Game::show_notice`].
+/// Should be drawn with ui camera ([`set_ui_camera`]) for expected behavior.
+pub fn draw_notice(pset: &PSet, text: &str, maybe_params: Option<TextParams>) {
+    let mut params = maybe_params.unwrap_or(TextParams {
+        font_size: DEFAULT_FONT_SIZE,
+        color: DEFAULT_FONT_COLOR,
+        ..Default::default()
+    });
+    params.font_size = (params.font_size as f32 * 0.6) as u16;
+    let margin = pset.logical.window.height / 50.0;
+    let padding = params.font_size as f32 / 3.0;
+    let dims = measure_text(text, params.font, params.font_size, 1.0);
+
+    let box_w = dims.width + padding * 2.0;
+    let box_h = params.font_size as f32 + padding * 2.0;
+    draw_rectangle(margin, margin, box_w, box_h, WHITE);
+    draw_rectangle_lines(margin, margin, box_w, box_h, 3.0, GRAY);
+    draw_text_ex(text, margin + padding, margin + padding + dims.offset_y, params);
 }
 
 /// Should be drawn with natural camera [`set_natural_camera`] for expected behavior 